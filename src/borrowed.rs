@@ -0,0 +1,136 @@
+//! A `Value` that borrows its strings from the input.
+//!
+//! Strings and object keys without escape sequences are slices of the
+//! parsed `&str`; only those that had to be unescaped are allocated.
+
+use std::borrow::Cow;
+
+use crate::{
+    error::JSONError, first_error, number::Number, options::ParserOptions, parser::Parser,
+    parser::Tree, token::Span, value,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Value<'a> {
+    String(Cow<'a, str>),
+    Number(Number),
+    Boolean(bool),
+    Object(Vec<(Cow<'a, str>, Value<'a>)>),
+    Array(Vec<Value<'a>>),
+    Null,
+}
+
+impl Value<'_> {
+    /// Copies the borrowed strings into an owned `value::Value`.
+    pub fn into_owned(self) -> value::Value {
+        match self {
+            Value::String(s) => value::Value::String(s.into_owned()),
+            Value::Number(n) => value::Value::Number(n),
+            Value::Boolean(b) => value::Value::Boolean(b),
+            Value::Object(members) => value::Value::Object(
+                members
+                    .into_iter()
+                    .map(|(key, value)| (key.into_owned(), Box::new(value.into_owned())))
+                    .collect(),
+            ),
+            Value::Array(elements) => {
+                value::Value::Array(elements.into_iter().map(Value::into_owned).collect())
+            }
+            Value::Null => value::Value::Null,
+        }
+    }
+}
+
+impl<'a> Tree<'a> for Value<'a> {
+    type Members = Vec<(Cow<'a, str>, Value<'a>)>;
+
+    fn string(value: Cow<'a, str>) -> Self {
+        Value::String(value)
+    }
+    fn number(value: Number) -> Self {
+        Value::Number(value)
+    }
+    fn boolean(value: bool) -> Self {
+        Value::Boolean(value)
+    }
+    fn null() -> Self {
+        Value::Null
+    }
+    fn array(elements: Vec<Self>) -> Self {
+        Value::Array(elements)
+    }
+    fn object(members: Self::Members) -> Self {
+        Value::Object(members)
+    }
+    fn push_member(object: &mut Self::Members, key: Cow<'a, str>, _: Span, value: Self) {
+        object.push((key, value));
+    }
+}
+
+/// Like `toyjson::from_str`, borrowing strings from `source`.
+pub fn from_str(source: &str) -> Result<Value<'_>, JSONError> {
+    from_str_with_options(source, ParserOptions::default())
+}
+
+/// Like `from_str`, in the dialect selected by `options`.
+pub fn from_str_with_options(source: &str, options: ParserOptions) -> Result<Value<'_>, JSONError> {
+    first_error(Parser::borrowed(source, options).parse_borrowed(source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_borrowed() {
+        let src = r#"{"key": "value", "esc\"aped": ["aéb", "あ", 1, true, null]}"#;
+        let value = from_str(src).unwrap();
+        let Value::Object(members) = &value else {
+            panic!("{:?}", value);
+        };
+        assert!(matches!(members[0].0, Cow::Borrowed("key")));
+        assert!(matches!(
+            members[0].1,
+            Value::String(Cow::Borrowed("value"))
+        ));
+        assert!(matches!(&members[1].0, Cow::Owned(key) if key == "esc\"aped"));
+        let Value::Array(elements) = &members[1].1 else {
+            panic!("{:?}", members[1].1);
+        };
+        assert_eq!(Value::String("a\u{e9}b".into()), elements[0]);
+        assert_eq!(Value::String("\u{3042}".into()), elements[1]);
+
+        assert_eq!(crate::from_str(src).unwrap(), value.into_owned());
+    }
+
+    #[test]
+    fn test_borrowed_options() {
+        let inputs = vec![
+            ("['single', 'it\\'s']", vec!["single", "it's"]),
+            (
+                "[\"multi\\\nline\", \"\u{1f600}\"]",
+                vec!["multiline", "\u{1f600}"],
+            ),
+        ];
+        for (input, expected) in inputs {
+            let value = from_str_with_options(input, ParserOptions::json5()).unwrap();
+            let expected = expected
+                .into_iter()
+                .map(|s| Value::String(s.into()))
+                .collect();
+            assert_eq!(Value::Array(expected), value);
+        }
+
+        let inputs = vec![
+            r#"{"a" "b"}"#,
+            r#"["a" "b\n"]"#,
+            r#"{"a": "unterminated}"#,
+            r#"{"a": 1,}"#,
+        ];
+        for input in inputs {
+            let expected = crate::from_str(input).unwrap_err();
+            let e = from_str(input).unwrap_err();
+            assert_eq!(format!("{:?}", expected), format!("{:?}", e));
+        }
+    }
+}
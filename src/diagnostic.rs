@@ -0,0 +1,355 @@
+//! rustc-style rendering of errors, quoting the input they were found in.
+
+use std::fmt::Write;
+
+use crate::{
+    error::{ErrorCode, JSONError},
+    number::Number,
+    token::{Location, Span},
+    value::Value,
+};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Formats a `JSONError` like
+///
+/// ```text
+/// error[E0008]: expected ',' or '}' after object member, found string "b"
+///  --> line 1, column 9
+///   |
+/// 1 | {"a": 1 "b": 2}
+///   |         ^^^
+///   = help: insert ','
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Renderer {
+    color: bool,
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Highlights the output with ANSI escape codes, for terminals.
+    pub fn color(mut self, enable: bool) -> Self {
+        self.color = enable;
+        self
+    }
+
+    /// Renders `error`, which must have been found in `source`.
+    pub fn render(&self, error: &JSONError, source: &str) -> String {
+        let (red, blue, bold, reset) = match self.color {
+            true => (RED, BLUE, BOLD, RESET),
+            false => ("", "", "", ""),
+        };
+        let mut out = format!(
+            "{red}{}[{}]{reset}{bold}: {}{reset}\n",
+            error.severity(),
+            error.code(),
+            error.message()
+        );
+        let Some(span) = error.span() else {
+            return out;
+        };
+        let (location, offset) = (span.start, span.start.offset);
+        if offset > source.len() || !source.is_char_boundary(offset) {
+            return out;
+        }
+
+        let is_break = |ch| ch == '\n' || ch == '\r';
+        let start = source[..offset].rfind(is_break).map_or(0, |i| i + 1);
+        let end = source[offset..]
+            .find(is_break)
+            .map_or(source.len(), |i| offset + i);
+        let underline_end = span.end.offset.clamp(offset, end);
+        // Tabs are kept so the carets line up however they are displayed.
+        let padding: String = source[start..offset]
+            .chars()
+            .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+            .collect();
+        let carets = "^".repeat(source[offset..underline_end].chars().count().max(1));
+
+        let number = location.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let _ = writeln!(
+            out,
+            "{gutter}{blue}-->{reset} line {}, column {}",
+            location.line, location.column
+        );
+        let _ = writeln!(out, "{gutter} {blue}|{reset}");
+        let _ = writeln!(out, "{blue}{number} |{reset} {}", &source[start..end]);
+        let _ = writeln!(out, "{gutter} {blue}|{reset} {padding}{red}{carets}{reset}");
+        for suggestion in error.suggestions() {
+            let _ = writeln!(out, "{gutter} {blue}={reset} help: {}", suggestion.message);
+        }
+        out
+    }
+}
+
+/// The errors as a JSON array of objects with `code`, `severity`,
+/// `message`, `span` (or null) and `suggestions`, for other tools to read.
+pub fn to_json(errors: &[JSONError]) -> Value {
+    Value::Array(
+        errors
+            .iter()
+            .map(|e| {
+                let suggestions = e
+                    .suggestions()
+                    .into_iter()
+                    .map(|s| {
+                        object(vec![
+                            ("message", Value::String(s.message)),
+                            ("span", span_json(s.span)),
+                            ("replacement", Value::String(s.replacement)),
+                        ])
+                    })
+                    .collect();
+                object(vec![
+                    ("code", string(e.code().as_str())),
+                    ("severity", Value::String(e.severity().to_string())),
+                    ("message", Value::String(e.message())),
+                    ("span", e.span().map_or(Value::Null, span_json)),
+                    ("suggestions", Value::Array(suggestions)),
+                ])
+            })
+            .collect(),
+    )
+}
+
+/// The errors as a SARIF 2.1.0 log (https://sarifweb.azurewebsites.net)
+/// with a single run, reporting them against the file at `uri`.
+pub fn to_sarif(errors: &[JSONError], uri: &str) -> Value {
+    let mut codes: Vec<ErrorCode> = errors.iter().map(JSONError::code).collect();
+    codes.sort_by_key(|code| code.as_str());
+    codes.dedup();
+    let rules = codes
+        .into_iter()
+        .map(|code| {
+            object(vec![
+                ("id", string(code.as_str())),
+                (
+                    "shortDescription",
+                    object(vec![("text", string(code.description()))]),
+                ),
+            ])
+        })
+        .collect();
+
+    let artifact = || object(vec![("uri", string(uri))]);
+    let results = errors
+        .iter()
+        .map(|e| {
+            let mut result = vec![
+                ("ruleId", string(e.code().as_str())),
+                ("level", Value::String(e.severity().to_string())),
+                (
+                    "message",
+                    object(vec![("text", Value::String(e.message()))]),
+                ),
+            ];
+            if let Some(span) = e.span() {
+                let location = object(vec![(
+                    "physicalLocation",
+                    object(vec![
+                        ("artifactLocation", artifact()),
+                        ("region", region(span)),
+                    ]),
+                )]);
+                result.push(("locations", Value::Array(vec![location])));
+            }
+            let fixes: Vec<Value> = e
+                .suggestions()
+                .into_iter()
+                .map(|s| {
+                    let replacement = object(vec![
+                        ("deletedRegion", region(s.span)),
+                        (
+                            "insertedContent",
+                            object(vec![("text", Value::String(s.replacement))]),
+                        ),
+                    ]);
+                    let change = object(vec![
+                        ("artifactLocation", artifact()),
+                        ("replacements", Value::Array(vec![replacement])),
+                    ]);
+                    object(vec![
+                        (
+                            "description",
+                            object(vec![("text", Value::String(s.message))]),
+                        ),
+                        ("artifactChanges", Value::Array(vec![change])),
+                    ])
+                })
+                .collect();
+            if !fixes.is_empty() {
+                result.push(("fixes", Value::Array(fixes)));
+            }
+            object(result)
+        })
+        .collect();
+
+    let run = object(vec![
+        (
+            "tool",
+            object(vec![(
+                "driver",
+                object(vec![
+                    ("name", string("toyjson")),
+                    ("rules", Value::Array(rules)),
+                ]),
+            )]),
+        ),
+        // `Location::column` counts characters
+        ("columnKind", string("unicodeCodePoints")),
+        ("results", Value::Array(results)),
+    ]);
+    object(vec![
+        (
+            "$schema",
+            string("https://json.schemastore.org/sarif-2.1.0.json"),
+        ),
+        ("version", string("2.1.0")),
+        ("runs", Value::Array(vec![run])),
+    ])
+}
+
+fn object(members: Vec<(&str, Value)>) -> Value {
+    Value::Object(
+        members
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), Box::new(value)))
+            .collect(),
+    )
+}
+
+fn string(s: &str) -> Value {
+    Value::String(s.to_string())
+}
+
+fn number(n: usize) -> Value {
+    Value::Number(Number::from(n as u64))
+}
+
+fn location_json(location: Location) -> Value {
+    object(vec![
+        ("line", number(location.line)),
+        ("column", number(location.column)),
+        ("offset", number(location.offset)),
+    ])
+}
+
+fn span_json(span: Span) -> Value {
+    object(vec![
+        ("start", location_json(span.start)),
+        ("end", location_json(span.end)),
+    ])
+}
+
+fn region(span: Span) -> Value {
+    object(vec![
+        ("startLine", number(span.start.line)),
+        ("startColumn", number(span.start.column)),
+        ("endLine", number(span.end.line)),
+        ("endColumn", number(span.end.column)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::ParserOptions;
+
+    #[test]
+    fn test_render() {
+        let inputs = vec![
+            (
+                "{\"a\": 1 \"b\": 2}",
+                concat!(
+                    "error[E0008]: expected ',' or '}' after object member, found string \"b\"\n",
+                    " --> line 1, column 9\n",
+                    "  |\n",
+                    "1 | {\"a\": 1 \"b\": 2}\n",
+                    "  |         ^^^\n",
+                    "  = help: insert ','\n",
+                ),
+            ),
+            (
+                "[\r\n\t1,\r\n\t\"\\x\"\r\n]",
+                concat!(
+                    "error[E0002]: invalid escape sequence '\\x'\n",
+                    " --> line 3, column 3\n",
+                    "  |\n",
+                    "3 | \t\"\\x\"\n",
+                    "  | \t ^\n",
+                ),
+            ),
+            (
+                "[1, 2",
+                concat!(
+                    "error[E0009]: expected ',' or ']' after array element, found end of input\n",
+                    " --> line 1, column 6\n",
+                    "  |\n",
+                    "1 | [1, 2\n",
+                    "  |      ^\n",
+                ),
+            ),
+        ];
+        for (input, expected) in inputs {
+            let e = crate::from_str(input).unwrap_err();
+            assert_eq!(expected, Renderer::new().render(&e, input));
+        }
+
+        let input = "\n".repeat(9) + "[1,]";
+        let e = crate::from_str_with_options(&input, ParserOptions::strict()).unwrap_err();
+        let rendered = Renderer::new().color(true).render(&e, &input);
+        assert!(rendered.starts_with("\x1b[1;31merror[E0010]\x1b[0m"));
+        assert!(rendered.contains("\x1b[1;34m10 |\x1b[0m [1,]"));
+        assert!(rendered.contains("  \x1b[1;31m^\x1b[0m\n"));
+        assert!(rendered.ends_with("help: remove the trailing comma\n"));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let (_, errors) = crate::parser::Parser::new("[1,]").parse_all();
+        assert_eq!(
+            concat!(
+                r#"[{"code":"E0010","severity":"error","message":"trailing comma","#,
+                r#""span":{"start":{"line":1,"column":3,"offset":2},"#,
+                r#""end":{"line":1,"column":4,"offset":3}},"#,
+                r#""suggestions":[{"message":"remove the trailing comma","#,
+                r#""span":{"start":{"line":1,"column":3,"offset":2},"#,
+                r#""end":{"line":1,"column":4,"offset":3}},"replacement":""}]}]"#,
+            ),
+            crate::to_string(&to_json(&errors)).unwrap()
+        );
+
+        let e = JSONError::from(std::io::Error::other("gone"));
+        let json = crate::to_string(&to_json(&[e])).unwrap();
+        assert!(json.contains(r#""code":"E0012""#) && json.contains(r#""span":null"#));
+    }
+
+    #[test]
+    fn test_to_sarif() {
+        let (_, errors) = crate::parser::Parser::new("{\"a\" 1}").parse_all();
+        let sarif = to_sarif(&errors, "config.json");
+        let text = crate::to_string(&sarif).unwrap();
+        assert!(text.starts_with(
+            r#"{"$schema":"https://json.schemastore.org/sarif-2.1.0.json","version":"2.1.0""#
+        ));
+        assert!(text.contains(
+            r#""rules":[{"id":"E0008","shortDescription":{"text":"unexpected token"}}]"#
+        ));
+        assert!(text.contains(r#""ruleId":"E0008","level":"error""#));
+        assert!(
+            text.contains(r#""region":{"startLine":1,"startColumn":6,"endLine":1,"endColumn":7}"#)
+        );
+        assert!(text.contains(r#""insertedContent":{"text":":"}"#));
+
+        // the output is itself valid JSON
+        assert_eq!(sarif, crate::from_str(&text).unwrap());
+    }
+}
@@ -1,14 +1,334 @@
-use crate::{
-    parser::Parser,
-    token::{Location, Token, TokenType},
-};
+use std::fmt::{Display, Formatter};
+
+use crate::token::{Location, Span, Token, TokenType};
 
 #[derive(Debug)]
 pub enum JSONError {
     IOError(std::io::Error),
     StringError(String),
-    LexcalError(String, Location),
-    UnexpectedToken(Token, Location),
+    LexcalError(ErrorCode, String, Span),
+    // the token found, where, and what was expected instead
+    UnexpectedToken(Box<Token>, Location, Expected),
+    // where the comma is
+    TrailingComma(Location),
+    DepthLimitExceeded(usize, Location),
+    // a `Value::Error` placeholder given to the serializer, with its message
+    Unserializable(String),
+    // a call not allowed in the current state, such as a value where a
+    // `JsonWriter` expects a key
+    Misuse(String),
+}
+
+/// Stable identifiers for kinds of errors, for tools that classify them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    UnterminatedString,
+    InvalidEscape,
+    InvalidSurrogate,
+    InvalidNumber,
+    UnexpectedCharacter,
+    UnterminatedComment,
+    InvalidUtf8,
+    UnexpectedToken,
+    UnexpectedEnd,
+    TrailingComma,
+    DepthLimitExceeded,
+    Io,
+    Other,
+    ControlCharacter,
+    Unserializable,
+    Misuse,
+}
+
+impl ErrorCode {
+    /// The code as `E` and four digits. Codes are never reused.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::UnterminatedString => "E0001",
+            ErrorCode::InvalidEscape => "E0002",
+            ErrorCode::InvalidSurrogate => "E0003",
+            ErrorCode::InvalidNumber => "E0004",
+            ErrorCode::UnexpectedCharacter => "E0005",
+            ErrorCode::UnterminatedComment => "E0006",
+            ErrorCode::InvalidUtf8 => "E0007",
+            ErrorCode::UnexpectedToken => "E0008",
+            ErrorCode::UnexpectedEnd => "E0009",
+            ErrorCode::TrailingComma => "E0010",
+            ErrorCode::DepthLimitExceeded => "E0011",
+            ErrorCode::Io => "E0012",
+            ErrorCode::Other => "E0013",
+            ErrorCode::ControlCharacter => "E0014",
+            ErrorCode::Unserializable => "E0015",
+            ErrorCode::Misuse => "E0016",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ErrorCode::UnterminatedString => "unterminated string",
+            ErrorCode::InvalidEscape => "invalid escape sequence",
+            ErrorCode::InvalidSurrogate => "unpaired UTF-16 surrogate",
+            ErrorCode::InvalidNumber => "invalid number",
+            ErrorCode::UnexpectedCharacter => "unexpected character",
+            ErrorCode::UnterminatedComment => "unterminated block comment",
+            ErrorCode::InvalidUtf8 => "invalid UTF-8",
+            ErrorCode::UnexpectedToken => "unexpected token",
+            ErrorCode::UnexpectedEnd => "unexpected end of input",
+            ErrorCode::TrailingComma => "trailing comma",
+            ErrorCode::DepthLimitExceeded => "nesting too deep",
+            ErrorCode::Io => "read error",
+            ErrorCode::Other => "other error",
+            ErrorCode::ControlCharacter => "unescaped control character in string",
+            ErrorCode::Unserializable => "value with no JSON form",
+            ErrorCode::Misuse => "call out of order",
+        }
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Every diagnostic makes the parse fail, so all of them are errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// What the parser would have accepted where it found an unexpected token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    Value,
+    ObjectKey,
+    Colon,
+    CommaOrRightBrace,
+    CommaOrRightBracket,
+    EndOfInput,
+    RecordSeparator,
+}
+
+impl Display for Expected {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expected::Value => f.write_str("a value"),
+            Expected::ObjectKey => f.write_str("an object key"),
+            Expected::Colon => f.write_str("':' after object key"),
+            Expected::CommaOrRightBrace => f.write_str("',' or '}' after object member"),
+            Expected::CommaOrRightBracket => f.write_str("',' or ']' after array element"),
+            Expected::EndOfInput => f.write_str("end of input"),
+            Expected::RecordSeparator => f.write_str("a record separator or end of input"),
+        }
+    }
+}
+
+/// A fix-it: replacing `span` with `replacement` resolves the error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub message: String,
+    pub span: Span,
+    pub replacement: String,
+}
+
+impl JSONError {
+    // The error for `token` where the grammar expected something else.
+    // Tokens the lexer could not make sense of carry their own message.
+    pub(crate) fn unexpected(token: Token, expected: Expected) -> JSONError {
+        match token.token_type {
+            TokenType::ILLEGAL => {
+                let span = token.span();
+                JSONError::LexcalError(
+                    token.error_code.unwrap_or(ErrorCode::UnexpectedCharacter),
+                    token.value.unwrap_or_default(),
+                    span,
+                )
+            }
+            _ => {
+                let location = token.location;
+                JSONError::UnexpectedToken(Box::new(token), location, expected)
+            }
+        }
+    }
+
+    // Moves the error into a larger input, for text that was parsed on its
+    // own but starts at `start`.
+    pub(crate) fn relative_to(mut self, start: Location) -> JSONError {
+        match &mut self {
+            JSONError::IOError(_)
+            | JSONError::StringError(_)
+            | JSONError::Unserializable(_)
+            | JSONError::Misuse(_) => {}
+            JSONError::LexcalError(code, message, span) => {
+                *span = span.relative_to(start);
+                // The message gives the offset of the bad byte, where the
+                // span starts.
+                if *code == ErrorCode::InvalidUtf8 && !message.is_empty() {
+                    *message = invalid_utf8(span.start.offset);
+                }
+            }
+            JSONError::UnexpectedToken(token, location, _) => {
+                token.location = token.location.relative_to(start);
+                token.end = token.end.relative_to(start);
+                *location = location.relative_to(start);
+            }
+            JSONError::TrailingComma(location) | JSONError::DepthLimitExceeded(_, location) => {
+                *location = location.relative_to(start)
+            }
+        }
+        self
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            JSONError::IOError(_) => ErrorCode::Io,
+            JSONError::StringError(_) => ErrorCode::Other,
+            JSONError::LexcalError(code, _, _) => *code,
+            JSONError::UnexpectedToken(token, _, _) if token.token_type == TokenType::EOF => {
+                ErrorCode::UnexpectedEnd
+            }
+            JSONError::UnexpectedToken(..) => ErrorCode::UnexpectedToken,
+            JSONError::TrailingComma(_) => ErrorCode::TrailingComma,
+            JSONError::DepthLimitExceeded(..) => ErrorCode::DepthLimitExceeded,
+            JSONError::Unserializable(_) => ErrorCode::Unserializable,
+            JSONError::Misuse(_) => ErrorCode::Misuse,
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    pub fn location(&self) -> Option<Location> {
+        self.span().map(|span| span.start)
+    }
+
+    /// The part of the input the error is about.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            JSONError::IOError(_)
+            | JSONError::StringError(_)
+            | JSONError::Unserializable(_)
+            | JSONError::Misuse(_) => None,
+            JSONError::LexcalError(_, _, span) => Some(*span),
+            JSONError::UnexpectedToken(token, _, _) => Some(token.span()),
+            JSONError::TrailingComma(location) | JSONError::DepthLimitExceeded(_, location) => {
+                Some(one_char(*location))
+            }
+        }
+    }
+
+    /// Edits that would fix the input, where there is an obvious one.
+    pub fn suggestions(&self) -> Vec<Suggestion> {
+        let insert = |message: &str, location: Location, text: &str| Suggestion {
+            message: message.to_string(),
+            span: Span {
+                start: location,
+                end: location,
+            },
+            replacement: text.to_string(),
+        };
+        match self {
+            JSONError::TrailingComma(location) => vec![Suggestion {
+                message: "remove the trailing comma".to_string(),
+                span: one_char(*location),
+                replacement: String::new(),
+            }],
+            JSONError::UnexpectedToken(token, location, expected)
+                if token.token_type != TokenType::EOF =>
+            {
+                match expected {
+                    Expected::Colon => vec![insert("insert ':'", *location, ":")],
+                    Expected::CommaOrRightBrace | Expected::CommaOrRightBracket => {
+                        vec![insert("insert ','", *location, ",")]
+                    }
+                    _ => vec![],
+                }
+            }
+            _ => vec![],
+        }
+    }
+
+    /// The description of the error without its location.
+    pub fn message(&self) -> String {
+        match self {
+            JSONError::IOError(e) => format!("read error: {}", e),
+            JSONError::StringError(message)
+            | JSONError::LexcalError(_, message, _)
+            | JSONError::Misuse(message) => message.clone(),
+            JSONError::UnexpectedToken(token, _, expected) => {
+                format!("expected {}, found {}", expected, describe(token))
+            }
+            JSONError::TrailingComma(_) => "trailing comma".to_string(),
+            JSONError::DepthLimitExceeded(depth, _) => {
+                format!("arrays and objects nested deeper than {}", depth)
+            }
+            JSONError::Unserializable(message) => {
+                format!("cannot serialize a value that failed to parse: {}", message)
+            }
+        }
+    }
+}
+
+// The message of an `ErrorCode::InvalidUtf8` error at byte `offset`.
+pub(crate) fn invalid_utf8(offset: usize) -> String {
+    format!("invalid UTF-8 at byte offset {}", offset)
+}
+
+// The span of the single-byte character at `location`.
+fn one_char(location: Location) -> Span {
+    let mut end = location;
+    end.column += 1;
+    end.offset += 1;
+    Span {
+        start: location,
+        end,
+    }
+}
+
+fn describe(token: &Token) -> String {
+    let value = token.value.as_deref().unwrap_or_default();
+    match token.token_type {
+        TokenType::String => format!("string \"{}\"", value),
+        TokenType::Number => format!("number {}", value),
+        TokenType::Ident => format!("identifier {}", value),
+        TokenType::Comment => "comment".to_string(),
+        TokenType::EOF => "end of input".to_string(),
+        TokenType::RecordSeparator => "record separator".to_string(),
+        _ => format!("'{}'", token.token_type),
+    }
+}
+
+impl Display for JSONError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.location() {
+            Some(location) => write!(
+                f,
+                "{} at line {}, column {}",
+                self.message(),
+                location.line,
+                location.column
+            ),
+            None => f.write_str(&self.message()),
+        }
+    }
+}
+
+impl std::error::Error for JSONError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JSONError::IOError(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 impl From<std::io::Error> for JSONError {
@@ -16,3 +336,66 @@ impl From<std::io::Error> for JSONError {
         JSONError::IOError(e)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let inputs = vec![
+            (
+                "[1 2]",
+                "expected ',' or ']' after array element, found number 2 at line 1, column 4",
+            ),
+            (
+                "{\"a\" 1}",
+                "expected ':' after object key, found number 1 at line 1, column 6",
+            ),
+            (
+                "{1: 2}",
+                "expected an object key, found number 1 at line 1, column 2",
+            ),
+            ("[1,]", "trailing comma at line 1, column 3"),
+            ("[}", "expected a value, found '}' at line 1, column 2"),
+            ("\n\"a\" #", "unexpected character '#' at line 2, column 5"),
+            (
+                "1 null",
+                "expected end of input, found 'null' at line 1, column 3",
+            ),
+        ];
+        for (input, expected) in inputs {
+            let e = crate::from_str(input).unwrap_err();
+            assert_eq!(expected, e.to_string());
+        }
+
+        let e = JSONError::from(std::io::Error::other("disk on fire"));
+        assert_eq!("read error: disk on fire", e.to_string());
+        assert!(std::error::Error::source(&e).is_some());
+    }
+
+    #[test]
+    fn test_code() {
+        let deep = "[".repeat(200);
+        let inputs = vec![
+            ("\"abc", ErrorCode::UnterminatedString),
+            ("\"\\q\"", ErrorCode::InvalidEscape),
+            ("\"\\ud800\"", ErrorCode::InvalidSurrogate),
+            ("1e999", ErrorCode::InvalidNumber),
+            ("#", ErrorCode::UnexpectedCharacter),
+            ("[1 2]", ErrorCode::UnexpectedToken),
+            ("[1", ErrorCode::UnexpectedEnd),
+            ("{\"a\": 1,}", ErrorCode::TrailingComma),
+            (&deep, ErrorCode::DepthLimitExceeded),
+        ];
+        for (input, code) in inputs {
+            let e = crate::from_str(input).unwrap_err();
+            assert_eq!(code, e.code(), "{}", input);
+            assert_eq!(Severity::Error, e.severity(), "{}", input);
+        }
+
+        let e = crate::from_str("{\"a\" 1}").unwrap_err();
+        let fix = &e.suggestions()[0];
+        assert_eq!((":", 5..5), (fix.replacement.as_str(), fix.span.range()));
+    }
+}
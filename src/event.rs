@@ -0,0 +1,411 @@
+//! Pull-based reading of a document as a sequence of events, for inputs
+//! too large to be held as a `Value`.
+//!
+//! Only the kinds of the arrays and objects that are still open are kept,
+//! so memory use grows with the nesting of the input, not with its size.
+
+use std::{io::Read, str::Chars};
+
+use crate::{
+    error::{ErrorCode, Expected, JSONError},
+    lexer::Lexer,
+    number::{self, Number},
+    options::ParserOptions,
+    parser,
+    source::{ByteSource, ReadSource, Source},
+    spanned::Spanned,
+    token::{Location, Token, TokenType},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    Key(String),
+    String(String),
+    Number(Number),
+    Boolean(bool),
+    Null,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Container {
+    Array,
+    Object,
+}
+
+// What the next token may be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    // at the top level or after a key and its colon
+    Value,
+    // just after `[`
+    FirstElement,
+    // after the comma at the location
+    Element(Location),
+    // just after `{`
+    FirstKey,
+    // after the comma at the location
+    Key(Location),
+    Colon,
+    // after a complete value, where the enclosing container decides
+    AfterValue,
+    Done,
+}
+
+/// Yields the events of a single document with their spans, checking the
+/// grammar as it goes. The first error is the last item.
+///
+/// ```
+/// use toyjson::event::{Event, EventReader};
+///
+/// let events: Vec<Event> = EventReader::new(r#"{"a": [1, null]}"#)
+///     .map(|e| e.unwrap().value)
+///     .collect();
+/// assert_eq!(Event::Key("a".to_string()), events[1]);
+/// assert_eq!(7, events.len());
+/// ```
+#[derive(Debug)]
+pub struct EventReader<T: Source> {
+    lexer: Lexer<T>,
+    grammar: Grammar,
+}
+
+impl<'a> EventReader<Chars<'a>> {
+    pub fn new(source: &'a str) -> Self {
+        EventReader::with_options(source, ParserOptions::default())
+    }
+
+    pub fn with_options(source: &'a str, options: ParserOptions) -> Self {
+        EventReader::from_lexer(Lexer::with_options(source, options), options)
+    }
+}
+
+impl<'a> EventReader<ByteSource<'a>> {
+    pub fn from_slice(source: &'a [u8], options: ParserOptions) -> Self {
+        EventReader::from_lexer(Lexer::from_slice(source, options), options)
+    }
+}
+
+impl<R: Read> EventReader<ReadSource<R>> {
+    pub fn from_reader(reader: R, options: ParserOptions) -> Self {
+        EventReader::from_lexer(Lexer::from_reader(reader, options), options)
+    }
+}
+
+impl<T: Source> EventReader<T> {
+    pub fn from_lexer(lexer: Lexer<T>, options: ParserOptions) -> Self {
+        EventReader {
+            lexer,
+            grammar: Grammar::new(options),
+        }
+    }
+
+    /// How many arrays and objects are open after the last event.
+    pub fn depth(&self) -> usize {
+        self.grammar.depth()
+    }
+
+    fn next_token(&mut self) -> Result<Token, JSONError> {
+        let mut token = self.lexer.next_token();
+        while token.token_type == TokenType::Comment {
+            token = self.lexer.next_token();
+        }
+        // A failed read looks like the end of the input to the lexer.
+        match self.lexer.take_io_error() {
+            Some(e) => Err(JSONError::IOError(e)),
+            None => Ok(token),
+        }
+    }
+
+    fn next_event(&mut self) -> Result<Option<Spanned<Event>>, JSONError> {
+        while !self.grammar.is_done() {
+            let token = self.next_token()?;
+            if let Some(event) = self.grammar.accept(token)? {
+                return Ok(Some(event));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<T: Source> Iterator for EventReader<T> {
+    type Item = Result<Spanned<Event>, JSONError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.next_event();
+        if event.is_err() {
+            self.grammar.state = State::Done;
+        }
+        event.transpose()
+    }
+}
+
+// The grammar of a document as a state machine fed one token at a time,
+// for readers that cannot recurse into arrays and objects.
+#[derive(Debug)]
+pub(crate) struct Grammar {
+    options: ParserOptions,
+    // the arrays and objects the next event is nested in
+    stack: Vec<Container>,
+    state: State,
+}
+
+impl Grammar {
+    pub(crate) fn new(options: ParserOptions) -> Self {
+        Grammar {
+            options,
+            stack: vec![],
+            state: State::Value,
+        }
+    }
+
+    pub(crate) fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    // After the end of the input, or an error.
+    pub(crate) fn is_done(&self) -> bool {
+        self.state == State::Done
+    }
+
+    // The event `token` stands for. Commas, colons and the end of the
+    // input have none.
+    pub(crate) fn accept(&mut self, token: Token) -> Result<Option<Spanned<Event>>, JSONError> {
+        let event = self.transition(token);
+        if event.is_err() {
+            self.state = State::Done;
+        }
+        event
+    }
+
+    fn transition(&mut self, token: Token) -> Result<Option<Spanned<Event>>, JSONError> {
+        let event = match (self.state, token.token_type.clone()) {
+            (State::FirstElement, TokenType::RightBracket) => self.close(token, Event::EndArray),
+            (State::FirstKey, TokenType::RightBrace) => self.close(token, Event::EndObject),
+            (State::Element(comma), TokenType::RightBracket) => {
+                self.trailing_comma(comma)?;
+                self.close(token, Event::EndArray)
+            }
+            (State::Key(comma), TokenType::RightBrace) => {
+                self.trailing_comma(comma)?;
+                self.close(token, Event::EndObject)
+            }
+            (State::Value | State::FirstElement | State::Element(_), _) => self.value(token)?,
+            (State::FirstKey | State::Key(_), _) => self.key(token)?,
+            (State::Colon, TokenType::Colon) => {
+                self.state = State::Value;
+                return Ok(None);
+            }
+            (State::Colon, _) => return Err(JSONError::unexpected(token, Expected::Colon)),
+            (State::AfterValue, token_type) => match (self.stack.last(), token_type) {
+                (None, TokenType::EOF) => {
+                    self.state = State::Done;
+                    return Ok(None);
+                }
+                (None, _) => return Err(JSONError::unexpected(token, Expected::EndOfInput)),
+                (Some(Container::Array), TokenType::Comma) => {
+                    self.state = State::Element(token.location);
+                    return Ok(None);
+                }
+                (Some(Container::Array), TokenType::RightBracket) => {
+                    self.close(token, Event::EndArray)
+                }
+                (Some(Container::Array), _) => {
+                    return Err(JSONError::unexpected(token, Expected::CommaOrRightBracket))
+                }
+                (Some(Container::Object), TokenType::Comma) => {
+                    self.state = State::Key(token.location);
+                    return Ok(None);
+                }
+                (Some(Container::Object), TokenType::RightBrace) => {
+                    self.close(token, Event::EndObject)
+                }
+                (Some(Container::Object), _) => {
+                    return Err(JSONError::unexpected(token, Expected::CommaOrRightBrace))
+                }
+            },
+            (State::Done, _) => return Ok(None),
+        };
+        Ok(Some(event))
+    }
+
+    fn value(&mut self, token: Token) -> Result<Spanned<Event>, JSONError> {
+        let span = token.span();
+        let event = match token.token_type {
+            TokenType::String => Event::String(token.value.unwrap_or_default()),
+            TokenType::Number => {
+                let value = token.value.unwrap_or_default();
+                match number::from_token(&value, &self.options) {
+                    Ok(n) => Event::Number(n),
+                    Err(e) => {
+                        return Err(JSONError::LexcalError(
+                            ErrorCode::InvalidNumber,
+                            e.to_string(),
+                            span,
+                        ))
+                    }
+                }
+            }
+            TokenType::True => Event::Boolean(true),
+            TokenType::False => Event::Boolean(false),
+            TokenType::Null => Event::Null,
+            TokenType::LeftBrace | TokenType::LeftBracket => {
+                if self.stack.len() >= self.options.max_depth {
+                    return Err(JSONError::DepthLimitExceeded(
+                        self.options.max_depth,
+                        token.location,
+                    ));
+                }
+                let (container, state, event) = match token.token_type {
+                    TokenType::LeftBrace => {
+                        (Container::Object, State::FirstKey, Event::StartObject)
+                    }
+                    _ => (Container::Array, State::FirstElement, Event::StartArray),
+                };
+                self.stack.push(container);
+                self.state = state;
+                return Ok(Spanned { value: event, span });
+            }
+            _ => return Err(JSONError::unexpected(token, Expected::Value)),
+        };
+        self.state = State::AfterValue;
+        Ok(Spanned { value: event, span })
+    }
+
+    fn key(&mut self, token: Token) -> Result<Spanned<Event>, JSONError> {
+        if !parser::is_key(&token, &self.options) {
+            return Err(JSONError::unexpected(token, Expected::ObjectKey));
+        }
+        self.state = State::Colon;
+        Ok(Spanned {
+            span: token.span(),
+            value: Event::Key(token.value.unwrap_or_default()),
+        })
+    }
+
+    fn close(&mut self, token: Token, event: Event) -> Spanned<Event> {
+        self.stack.pop();
+        self.state = State::AfterValue;
+        Spanned {
+            value: event,
+            span: token.span(),
+        }
+    }
+
+    fn trailing_comma(&self, comma: Location) -> Result<(), JSONError> {
+        match self.options.allow_trailing_commas {
+            true => Ok(()),
+            false => Err(JSONError::TrailingComma(comma)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events() {
+        let src = "{\"a\": [1, \"x\", true],\n \"b\": {}, \"c\": null}";
+        let events: Vec<_> = EventReader::new(src).map(Result::unwrap).collect();
+        let expected = vec![
+            (Event::StartObject, "{"),
+            (Event::Key("a".to_string()), "\"a\""),
+            (Event::StartArray, "["),
+            (Event::Number(Number::from(1u64)), "1"),
+            (Event::String("x".to_string()), "\"x\""),
+            (Event::Boolean(true), "true"),
+            (Event::EndArray, "]"),
+            (Event::Key("b".to_string()), "\"b\""),
+            (Event::StartObject, "{"),
+            (Event::EndObject, "}"),
+            (Event::Key("c".to_string()), "\"c\""),
+            (Event::Null, "null"),
+            (Event::EndObject, "}"),
+        ];
+        let found: Vec<_> = events
+            .iter()
+            .map(|e| (e.value.clone(), &src[e.span.range()]))
+            .collect();
+        assert_eq!(expected, found);
+        let b = &events[7].span.start;
+        assert_eq!((2, 2), (b.line, b.column));
+
+        let events: Vec<_> = EventReader::new(" 1.5 ").map(Result::unwrap).collect();
+        assert_eq!(1, events.len());
+        assert_eq!(
+            Event::Number(Number::from_f64(1.5).unwrap()),
+            events[0].value
+        );
+    }
+
+    #[test]
+    fn test_event_options() {
+        let src = "// config\n{port: 0x50, tags: ['a',], /* ok */ }";
+        let events: Vec<_> = EventReader::with_options(src, ParserOptions::json5())
+            .map(|e| e.unwrap().value)
+            .collect();
+        assert_eq!(
+            vec![
+                Event::StartObject,
+                Event::Key("port".to_string()),
+                Event::Number(Number::from(80u64)),
+                Event::Key("tags".to_string()),
+                Event::StartArray,
+                Event::String("a".to_string()),
+                Event::EndArray,
+                Event::EndObject,
+            ],
+            events
+        );
+    }
+
+    #[test]
+    fn test_event_errors() {
+        let deep = "[".repeat(200);
+        let inputs = vec![
+            "",
+            "[1 2]",
+            "{\"a\" 1}",
+            "{1: 2}",
+            "[1,]",
+            "{\"a\": 1,}",
+            "[,]",
+            "[}",
+            "{\"a\": 1]",
+            "1 null",
+            "[1, 2",
+            "\"abc",
+            "[1e999]",
+            "[#]",
+            &deep,
+        ];
+        for input in inputs {
+            let expected = crate::from_str(input).unwrap_err();
+            let mut reader = EventReader::new(input);
+            let e = reader.find_map(Result::err).unwrap();
+            assert_eq!(format!("{:?}", expected), format!("{:?}", e), "{}", input);
+            assert!(reader.next().is_none());
+        }
+    }
+
+    #[test]
+    fn test_event_reader_large() {
+        // a long array read in constant memory, folded as it streams by
+        let src = format!("[{}0]", "{\"n\": 2, \"s\": \"x\"},".repeat(50_000));
+        let mut reader = EventReader::from_reader(src.as_bytes(), ParserOptions::default());
+        let (mut sum, mut max_depth) = (0, 0);
+        while let Some(event) = reader.next() {
+            if let Event::Number(n) = event.unwrap().value {
+                sum += n.as_u64().unwrap();
+            }
+            max_depth = max_depth.max(reader.depth());
+        }
+        assert_eq!(100_000, sum);
+        assert_eq!(2, max_depth);
+        assert_eq!(0, reader.depth());
+    }
+}
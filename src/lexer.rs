@@ -1,204 +1,797 @@
-use std::{
-    str::{Bytes, Chars},
-    string::ParseError,
-};
+use std::{io, io::Read, str::Chars};
 
 use crate::{
-    error::JSONError,
-    token::{lookup_ident, Location, Token, TokenType},
+    error::{invalid_utf8, ErrorCode},
+    options::ParserOptions,
+    source::{ByteSource, ReadSource, Source},
+    token::{Location, Token, TokenType},
 };
 
 #[derive(Debug, Clone)]
-pub struct Lexer<T: Iterator<Item = char> + Clone> {
+pub struct Lexer<T: Source> {
     source: T,
     location: Location,
     ch: char,
+    // length in bytes of `ch`, 0 at the end of the input
+    ch_len: usize,
+    // one char of lookahead, so that sources need not be `Clone`
+    peeked: Option<Option<char>>,
+    options: ParserOptions,
+    utf8_error_reported: bool,
+    borrow_strings: bool,
+    // the last char read and where it was
+    prev: Option<(Location, char)>,
+    // a token cut short at the end of the previous chunk, to be finished
+    partial: Option<Partial>,
+    // set when the source ran out in the middle of a chunked input
+    checkpoint: Option<Checkpoint>,
+}
+
+// What a lexer was in the middle of when its chunk of the input ended.
+// Escapes are not split: one cut short is lexed again from its `\`, so it
+// is not part of the state.
+#[derive(Debug, Clone)]
+pub(crate) enum Partial {
+    // never borrowed: `value` is the whole of the contents so far
+    String {
+        start: Location,
+        quote: char,
+        value: String,
+    },
+    Number {
+        start: Location,
+        text: String,
+        state: NumberState,
+    },
+    // `number` after a sign, as in `-Infinity`
+    Ident {
+        start: Location,
+        name: String,
+        number: bool,
+    },
+    Comment {
+        start: Location,
+        text: String,
+        state: CommentState,
+    },
+}
+
+// How far a number has got, so that it can go on in the next chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NumberState {
+    // before the first digit, after an optional sign
+    Sign,
+    // after a leading `0`
+    Zero,
+    // after `0x`, with no digit yet
+    Hex,
+    HexDigits,
+    Int,
+    AfterInt,
+    // after the decimal point; `int` if there are digits before it
+    Point { int: bool },
+    Frac,
+    AfterFrac,
+    // after the `e`
+    Exp,
+    // after the sign of the exponent, or the `e` if there is none
+    ExpSign,
+    ExpDigits,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CommentState {
+    // after the first `/`
+    Slash,
+    Line,
+    Block,
+    // after a `*` in a block comment
+    BlockStar,
+}
+
+// Where a lexer of chunked input stopped: the last char it consumed, and
+// the token it was in the middle of. `Lexer::resume` goes on from there.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Checkpoint {
+    prev: Option<(Location, char)>,
+    partial: Option<Partial>,
+}
+
+impl Checkpoint {
+    // The byte offset of the first char not consumed yet.
+    pub(crate) fn offset(&self) -> usize {
+        self.prev
+            .map_or(0, |(location, ch)| location.offset + ch.len_utf8())
+    }
+}
+
+impl<'a> Lexer<Chars<'a>> {
+    pub fn new(source: &'a str) -> Self {
+        Lexer::with_options(source, ParserOptions::default())
+    }
+
+    pub fn with_options(source: &'a str, options: ParserOptions) -> Self {
+        Lexer::from_source(source.chars(), options)
+    }
+}
+
+impl<'a> Lexer<ByteSource<'a>> {
+    /// Lexes UTF-8 bytes directly; invalid UTF-8 becomes an ILLEGAL token
+    /// that names its byte offset.
+    pub fn from_slice(source: &'a [u8], options: ParserOptions) -> Self {
+        Lexer::from_source(ByteSource::new(source), options)
+    }
+}
+
+impl<R: Read> Lexer<ReadSource<R>> {
+    /// Lexes UTF-8 read incrementally from `reader`. A read error ends the
+    /// input and is kept for `take_io_error`.
+    pub fn from_reader(reader: R, options: ParserOptions) -> Self {
+        Lexer::from_source(ReadSource::new(reader), options)
+    }
 }
 
 impl<T> Lexer<T>
 where
-    T: Iterator<Item = char> + Clone,
+    T: Source,
 {
-    pub fn new<'a>(source: &'a str) -> Lexer<Chars> {
+    pub fn from_source(source: T, options: ParserOptions) -> Self {
+        Lexer::resume(source, options, Checkpoint::default())
+    }
+
+    // Lexes `source` as the rest of an input, from where the lexer of the
+    // part before it stopped.
+    pub(crate) fn resume(source: T, options: ParserOptions, checkpoint: Checkpoint) -> Self {
+        // `next` moves onto the first char, counting it after `prev`
+        let (location, ch) = checkpoint.prev.unwrap_or((Location::new(1, 0), ' '));
         let mut lex = Lexer {
-            source: source.chars(),
-            location: Location::new(1, 1),
-            ch: ' ',
+            source,
+            location,
+            ch,
+            ch_len: checkpoint.prev.map_or(0, |(_, ch)| ch.len_utf8()),
+            peeked: None,
+            options,
+            utf8_error_reported: false,
+            borrow_strings: false,
+            prev: checkpoint.prev,
+            partial: checkpoint.partial,
+            checkpoint: None,
         };
 
         lex.next();
-        return lex;
+        lex
     }
 
-    fn new_token(&self, token_type: TokenType) -> Token {
+    fn new_token(&self, location: Location, token_type: TokenType) -> Token {
         Token {
-            location: self.location.clone(),
+            location,
+            end: self.location,
             token_type,
             value: None,
+            error_code: None,
+            prefix_end: None,
         }
     }
 
-    fn new_literal(&self, token_type: TokenType, value: String) -> Token {
+    fn new_literal(&self, location: Location, token_type: TokenType, value: String) -> Token {
         Token {
-            location: self.location.clone(),
+            location,
+            end: self.location,
             token_type,
             value: Some(value),
+            error_code: None,
+            prefix_end: None,
+        }
+    }
+
+    // Leaves the part of a string before its first escape out of
+    // `Token::value`, for the caller to slice out of the input.
+    pub(crate) fn borrow_strings(mut self) -> Self {
+        self.borrow_strings = true;
+        self
+    }
+
+    // Where the lexer stopped if its source ran out in the middle of a
+    // chunked input. `next_token` returns EOF then.
+    pub(crate) fn take_checkpoint(&mut self) -> Option<Checkpoint> {
+        self.checkpoint.take()
+    }
+
+    // Out of input for now, with more to come.
+    fn suspended(&self) -> bool {
+        self.at_eof() && self.source.suspended()
+    }
+
+    // Stops at the end of the chunk, to go on after `prev` with `partial`.
+    fn suspend(&mut self, partial: Option<Partial>, prev: Option<(Location, char)>) -> Token {
+        self.checkpoint = Some(Checkpoint { prev, partial });
+        self.new_token(self.location, TokenType::EOF)
+    }
+
+    pub fn take_io_error(&mut self) -> Option<io::Error> {
+        self.source.take_io_error()
+    }
+
+    // ILLEGAL tokens carry the reason in `value` so the parser can report it.
+    fn error(&self, location: Location, code: ErrorCode, message: &str) -> Token {
+        let mut token = self.new_literal(location, TokenType::ILLEGAL, message.to_string());
+        token.error_code = Some(code);
+        token
+    }
+
+    // For input that ends too early: when the source stopped at invalid
+    // UTF-8, that is reported (once) instead of `message`.
+    fn eof_error(&mut self, location: Location, code: ErrorCode, message: &str) -> Token {
+        match self.source.utf8_error() {
+            Some(offset) if !self.utf8_error_reported => {
+                self.utf8_error_reported = true;
+                let message = invalid_utf8(offset);
+                self.error(self.location, ErrorCode::InvalidUtf8, &message)
+            }
+            _ => self.error(location, code, message),
         }
     }
 
     pub fn next_token(&mut self) -> Token {
-        let token = match self.ch {
-            '"' => {
-                let val = self.string();
-                self.new_literal(TokenType::String, val)
+        let result = match self.partial.take() {
+            None => return self.lex_token(),
+            Some(Partial::Comment { start, text, state }) => {
+                match self.comment(start, text, state) {
+                    Ok(comment) if self.options.emit_comments => return comment,
+                    Ok(_) => return self.lex_token(),
+                    Err(e) => return e,
+                }
+            }
+            Some(Partial::String {
+                start,
+                quote,
+                value,
+            }) => self.string(start, quote, value),
+            Some(Partial::Number { start, text, state }) => self.number(start, text, state),
+            Some(Partial::Ident {
+                start,
+                name,
+                number,
+            }) => self.ident(start, name, number),
+        };
+        result.unwrap_or_else(|e| e)
+    }
+
+    fn lex_token(&mut self) -> Token {
+        self.ignore_space();
+        while self.ch == '/' && self.options.allow_comments {
+            let location = self.location;
+            self.next();
+            match self.comment(location, String::from("/"), CommentState::Slash) {
+                Ok(comment) if self.options.emit_comments => return comment,
+                Ok(_) => {}
+                Err(e) => return e,
+            }
+            self.ignore_space();
+        }
+        let location = self.location;
+        match self.ch {
+            '"' | '\'' if self.ch == '"' || self.options.allow_single_quotes => {
+                let quote = self.ch;
+                self.next();
+                self.string(location, quote, String::new())
+                    .unwrap_or_else(|e| e)
+            }
+            ':' | ',' | '[' | ']' | '{' | '}' => {
+                let token_type = TokenType::from(self.ch);
+                self.next();
+                self.new_token(location, token_type)
+            }
+            '\u{1e}' if self.options.json_seq => {
+                self.next();
+                self.new_token(location, TokenType::RecordSeparator)
             }
-            ':' => self.new_token(TokenType::Colon),
-            ',' => self.new_token(TokenType::Comma),
-            '[' => self.new_token(TokenType::LeftBracket),
-            ']' => self.new_token(TokenType::RightBracket),
-            '{' => self.new_token(TokenType::LeftBrace),
-            '}' => self.new_token(TokenType::RightBrace),
-            '\0' => self.new_token(TokenType::EOF),
+            '\0' if self.suspended() => self.suspend(None, self.prev),
+            '\0' if self.at_eof()
+                && self.source.utf8_error().is_some()
+                && !self.utf8_error_reported =>
+            {
+                self.eof_error(location, ErrorCode::InvalidUtf8, "")
+            }
+            '\0' if self.at_eof() => self.new_token(location, TokenType::EOF),
             _ => {
                 if self.is_letter() {
-                    let ident = self.ident();
-                    let token_type = TokenType::lookup_ident(&ident);
-                    self.new_literal(token_type, ident)
-                } else if self.is_digit(true) {
-                    let num = self.number();
-                    self.new_literal(TokenType::Number, num)
+                    self.ident(location, String::new(), false)
+                        .unwrap_or_else(|e| e)
+                } else if self.is_digit(true)
+                    || (self.options.json5_extensions && matches!(self.ch, '+' | '.'))
+                {
+                    let mut text = String::new();
+                    if self.ch == '-' || self.ch == '+' {
+                        text.push(self.ch);
+                        self.next();
+                    }
+                    self.number(location, text, NumberState::Sign)
+                        .unwrap_or_else(|e| e)
                 } else {
-                    self.new_token(TokenType::ILLEGAL)
+                    let ch = self.ch;
+                    self.next();
+                    self.error(
+                        location,
+                        ErrorCode::UnexpectedCharacter,
+                        &format!("unexpected character '{}'", ch.escape_debug()),
+                    )
                 }
             }
-        };
-        self.next();
-        token
+        }
     }
 
     fn ignore_space(&mut self) {
-        while self.ch == '\n' || self.ch == '\r' || self.ch == '\t' || self.ch == ' ' {
-            match self.ch {
-                '\n' => {
-                    self.location.next_line();
-                    self.source.next();
+        while matches!(self.ch, '\n' | '\r' | '\t' | ' ')
+            || (self.options.json5_extensions && self.is_json5_space())
+        {
+            self.next();
+        }
+    }
+
+    // `ch` is '\0' at the end of the input, but the input may contain NUL too.
+    fn at_eof(&self) -> bool {
+        self.ch == '\0' && self.ch_len == 0
+    }
+
+    // Strict JSON requires all of U+0000 to U+001F to be escaped in strings;
+    // JSON5 only line terminators.
+    fn control_character_allowed(&self) -> bool {
+        self.options.json5_extensions && !matches!(self.ch, '\n' | '\r')
+    }
+
+    // JSON5 also allows VT, FF, NBSP, BOM, the line/paragraph separators and
+    // every other Unicode space separator.
+    fn is_json5_space(&self) -> bool {
+        (self.ch.is_whitespace() && self.ch != '\u{85}') || self.ch == '\u{feff}'
+    }
+
+    // `// ...` up to the end of the line or `/* ... */`, from `state` on.
+    fn comment(
+        &mut self,
+        start: Location,
+        mut text: String,
+        mut state: CommentState,
+    ) -> Result<Token, Token> {
+        loop {
+            if self.suspended() {
+                let partial = Partial::Comment { start, text, state };
+                return Err(self.suspend(Some(partial), self.prev));
+            }
+            state = match (state, self.ch) {
+                (CommentState::Slash, '/') => CommentState::Line,
+                (CommentState::Slash, '*') => CommentState::Block,
+                (CommentState::Slash, _) => {
+                    return Err(self.error(
+                        start,
+                        ErrorCode::UnexpectedCharacter,
+                        "unexpected character '/'",
+                    ))
                 }
-                '\r' => {
-                    self.next();
-                    self.source.next();
-                    if self.ch == '\n' {
-                        self.location.next_line();
-                        self.source.next();
-                    }
+                (CommentState::Line, '\n' | '\r') => break,
+                (CommentState::Line, '\0') if self.at_eof() => break,
+                (CommentState::Block | CommentState::BlockStar, '\0') if self.at_eof() => {
+                    return Err(self.eof_error(
+                        start,
+                        ErrorCode::UnterminatedComment,
+                        "unterminated block comment",
+                    ))
                 }
-                '\t' | ' ' => {
-                    self.location.next_column();
+                (CommentState::BlockStar, '/') => {
+                    text.push(self.ch);
+                    self.next();
+                    break;
                 }
-                _ => break,
-            }
+                (CommentState::Block | CommentState::BlockStar, '*') => CommentState::BlockStar,
+                (CommentState::BlockStar, _) => CommentState::Block,
+                (state, _) => state,
+            };
+            text.push(self.ch);
             self.next();
         }
+        Ok(self.new_literal(start, TokenType::Comment, text))
     }
 
-    fn string(&mut self) -> String {
-        let mut value = String::new();
+    // On error the rest of the string is skipped so that lexing can resume
+    // after the closing quote. The opening quote decides the closing one.
+    // Lexing starts at the char after the opening quote, or where the
+    // previous chunk ended, with the contents so far in `value`.
+    //
+    // When borrowing strings, nothing is copied until the first escape.
+    fn string(&mut self, start: Location, quote: char, mut value: String) -> Result<Token, Token> {
+        let mut prefix_end = None;
         loop {
-            self.next_with_space();
             match self.ch {
-                '"' => break,
-                _ => {
-                    value.push(self.ch);
+                c if c == quote => break,
+                '\0' if self.suspended() => {
+                    let partial = Partial::String {
+                        start,
+                        quote,
+                        value,
+                    };
+                    return Err(self.suspend(Some(partial), self.prev));
+                }
+                '\0' if self.at_eof() => {
+                    return Err(self.eof_error(
+                        start,
+                        ErrorCode::UnterminatedString,
+                        "unterminated string",
+                    ))
+                }
+                c if c < ' ' && !self.control_character_allowed() => {
+                    let e = self.error(
+                        self.location,
+                        ErrorCode::ControlCharacter,
+                        &format!("unescaped control character U+{:04X} in string", c as u32),
+                    );
+                    self.skip_string(quote);
+                    return Err(e);
                 }
+                '\\' => {
+                    if self.borrow_strings && prefix_end.is_none() {
+                        prefix_end = Some(self.location.offset);
+                    }
+                    let before = self.prev;
+                    let escaped = self.escape();
+                    // It needed more than the chunk had, so it is decided
+                    // again once the rest is there.
+                    if self.source.suspended() {
+                        let partial = Partial::String {
+                            start,
+                            quote,
+                            value,
+                        };
+                        return Err(self.suspend(Some(partial), before));
+                    }
+                    match escaped {
+                        Ok(Some(ch)) => value.push(ch),
+                        Ok(None) => {}
+                        Err(e) => {
+                            self.skip_string(quote);
+                            return Err(e);
+                        }
+                    }
+                }
+                _ if self.borrow_strings && prefix_end.is_none() => {}
+                _ => value.push(self.ch),
             }
+            self.next();
         }
-        value
+        let end = prefix_end.unwrap_or(self.location.offset);
+        self.next();
+        let mut token = self.new_literal(start, TokenType::String, value);
+        if self.borrow_strings {
+            if prefix_end.is_none() {
+                token.value = None;
+            }
+            token.prefix_end = Some(end);
+        }
+        Ok(token)
     }
-    // 12345 | 123.45 | 123.45e6 | 123.45e+6 | 123.45e-6
-    fn number(&mut self) -> String {
-        let mut value = String::new();
 
-        if self.ch == '-' {
-            value.push(self.ch);
-            self.next();
+    // Decodes the escape sequence starting at the current `\`, leaving
+    // `self.ch` on its last character. `None` is a JSON5 line continuation.
+    fn escape(&mut self) -> Result<Option<char>, Token> {
+        let location = self.location;
+        self.next();
+        if self.options.json5_extensions {
+            if let Some(ch) = self.json5_escape(location)? {
+                return Ok(ch);
+            }
         }
+        let ch = match self.ch {
+            '"' => '"',
+            '\'' if self.options.allow_single_quotes => '\'',
+            '\\' => '\\',
+            '/' => '/',
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => return self.unicode_escape(location).map(Some),
+            '\0' if self.at_eof() => {
+                return Err(self.eof_error(
+                    location,
+                    ErrorCode::InvalidEscape,
+                    "unterminated escape sequence",
+                ))
+            }
+            c => {
+                return Err(self.error(
+                    location,
+                    ErrorCode::InvalidEscape,
+                    &format!("invalid escape sequence '\\{}'", c),
+                ));
+            }
+        };
+        Ok(Some(ch))
+    }
 
-        if !matches!(self.ch, '0'..='9') {
-            return value;
-        }
-        if matches!(self.ch, '0') {
-            value.push(self.ch);
-            self.next();
-            if matches!(self.ch, '1'..='9') {
-                return value;
+    // The escapes JSON5 adds on top of JSON: line continuations, \v, \0,
+    // \xHH and any other non-digit character standing for itself. Returns
+    // `None` for the escapes JSON already has.
+    fn json5_escape(&mut self, location: Location) -> Result<Option<Option<char>>, Token> {
+        let peek = self.peek();
+        let ch = match self.ch {
+            '\n' | '\u{2028}' | '\u{2029}' => None,
+            '\r' => {
+                if peek == Some('\n') {
+                    self.next();
+                }
+                None
             }
-        } else if matches!(self.ch, '1'..='9') {
-            value.push(self.ch);
-            self.next();
-            while matches!(self.ch, '0'..='9') {
-                value.push(self.ch);
-                self.next();
+            'v' => Some('\u{b}'),
+            '0' if !peek.is_some_and(|c| c.is_ascii_digit()) => Some('\0'),
+            'x' => {
+                let mut code = 0;
+                for _ in 0..2 {
+                    self.next();
+                    match self.ch.to_digit(16) {
+                        Some(d) => code = code * 16 + d,
+                        None => {
+                            let message = format!("invalid hex digit '{}' in \\x escape", self.ch);
+                            return Err(self.error(location, ErrorCode::InvalidEscape, &message));
+                        }
+                    }
+                }
+                char::from_u32(code)
             }
-        }
+            '"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't' | 'u' | '\0' => return Ok(None),
+            // \0 followed by a digit and \1 to \9 are legacy octal escapes
+            '0'..='9' => return Ok(None),
+            c => Some(c),
+        };
+        Ok(Some(ch))
+    }
 
-        if matches!(self.ch, '.') {
-            value.push(self.ch);
-            self.next();
-            while matches!(self.ch, '0'..='9') {
-                value.push(self.ch);
+    // \uXXXX, where a high surrogate must be followed by a \uXXXX low
+    // surrogate and the pair is combined into one scalar value.
+    fn unicode_escape(&mut self, location: Location) -> Result<char, Token> {
+        let hi = self.hex4(location)?;
+        let code = match hi {
+            0xD800..=0xDBFF => {
+                self.next();
+                if self.ch != '\\' {
+                    return Err(self.error(
+                        location,
+                        ErrorCode::InvalidSurrogate,
+                        "lone leading surrogate in \\u escape",
+                    ));
+                }
+                let low_location = self.location;
                 self.next();
+                if self.ch != 'u' {
+                    // step over the escaped character so it is not mistaken
+                    // for the closing quote or another escape
+                    if !self.at_eof() {
+                        self.next();
+                    }
+                    return Err(self.error(
+                        location,
+                        ErrorCode::InvalidSurrogate,
+                        "lone leading surrogate in \\u escape",
+                    ));
+                }
+                let lo = self.hex4(low_location)?;
+                if !(0xDC00..=0xDFFF).contains(&lo) {
+                    return Err(self.error(
+                        low_location,
+                        ErrorCode::InvalidSurrogate,
+                        "expected trailing surrogate in \\u escape",
+                    ));
+                }
+                0x10000 + ((hi - 0xD800) << 10) + (lo - 0xDC00)
             }
-        }
-
-        if matches!(self.ch, 'e' | 'E') {
-            value.push(self.ch);
-            self.next();
-
-            if !matches!(self.ch, '-' | '+') {
-                return value;
+            0xDC00..=0xDFFF => {
+                return Err(self.error(
+                    location,
+                    ErrorCode::InvalidSurrogate,
+                    "lone trailing surrogate in \\u escape",
+                ));
             }
+            _ => hi,
+        };
+        // Surrogates are handled above, so every remaining code is a scalar value.
+        Ok(char::from_u32(code).unwrap())
+    }
 
-            value.push(self.ch);
+    fn hex4(&mut self, location: Location) -> Result<u32, Token> {
+        let mut code = 0;
+        for _ in 0..4 {
             self.next();
-
-            while matches!(self.ch, '0'..='9') {
-                value.push(self.ch);
-                self.next();
+            match self.ch.to_digit(16) {
+                Some(d) => code = code * 16 + d,
+                None if self.at_eof() => {
+                    return Err(self.eof_error(
+                        location,
+                        ErrorCode::InvalidEscape,
+                        "unterminated escape sequence",
+                    ));
+                }
+                None => {
+                    let message = format!("invalid hex digit '{}' in \\u escape", self.ch);
+                    return Err(self.error(location, ErrorCode::InvalidEscape, &message));
+                }
             }
         }
-        return value;
+        Ok(code)
     }
 
-    fn ident(&mut self) -> String {
-        let mut name = String::new();
-        name.push(self.ch);
-        let mut peek = self.clone().peekable();
+    fn skip_string(&mut self, quote: char) {
+        loop {
+            match self.ch {
+                c if c == quote => {
+                    self.next();
+                    break;
+                }
+                '\0' if self.at_eof() => break,
+                '\\' => {
+                    self.next();
+                    if !self.at_eof() {
+                        self.next();
+                    }
+                }
+                _ => {
+                    self.next();
+                }
+            }
+        }
+    }
 
+    // 12345 | 123.45 | 123.45e6 | 123.45e+6 | 123.45e-6
+    // JSON5 adds +1, .5, 5., 0x1F, +Infinity and NaN with a sign.
+    //
+    // `text` holds the sign, if any, or what the previous chunk had of the
+    // number, and `state` how far that got.
+    fn number(
+        &mut self,
+        start: Location,
+        mut text: String,
+        mut state: NumberState,
+    ) -> Result<Token, Token> {
+        let json5 = self.options.json5_extensions;
         loop {
-            let p = peek.peek().unwrap();
-            if matches!(p, ',' | ';' | ':' | ']' | '}' | ' ') {
-                break;
+            if self.suspended() {
+                let partial = Partial::Number { start, text, state };
+                return Err(self.suspend(Some(partial), self.prev));
             }
-            name.push(p.clone());
-            peek.next();
+            let ch = self.ch;
+            state = match state {
+                // -Infinity, which is only accepted with allow_nan_infinity
+                NumberState::Sign if self.is_letter() => return self.ident(start, text, true),
+                NumberState::Sign => match ch {
+                    '0' => NumberState::Zero,
+                    '1'..='9' => NumberState::Int,
+                    '.' if json5 => NumberState::Point { int: false },
+                    _ => {
+                        let message = format!("expected a digit after '{}'", text);
+                        return Err(self.number_error(start, &message));
+                    }
+                },
+                NumberState::Zero if json5 && matches!(ch, 'x' | 'X') => NumberState::Hex,
+                NumberState::Zero if ch.is_ascii_digit() => {
+                    return Err(self.number_error(start, "leading zeros are not allowed"));
+                }
+                NumberState::Hex | NumberState::HexDigits if ch.is_ascii_hexdigit() => {
+                    NumberState::HexDigits
+                }
+                NumberState::Hex => {
+                    return Err(self.number_error(start, "expected hexadecimal digits after '0x'"));
+                }
+                NumberState::Int if ch.is_ascii_digit() => NumberState::Int,
+                NumberState::Zero | NumberState::Int => {
+                    state = NumberState::AfterInt;
+                    continue;
+                }
+                NumberState::AfterInt if ch == '.' => NumberState::Point { int: true },
+                NumberState::Point { .. } | NumberState::Frac if ch.is_ascii_digit() => {
+                    NumberState::Frac
+                }
+                // JSON5 allows either side of the point to be empty, not both.
+                NumberState::Point { int } if !(json5 && int) => {
+                    return Err(self.number_error(start, "expected digits after the decimal point"));
+                }
+                NumberState::AfterInt | NumberState::Point { .. } | NumberState::Frac => {
+                    state = NumberState::AfterFrac;
+                    continue;
+                }
+                NumberState::AfterFrac if matches!(ch, 'e' | 'E') => NumberState::Exp,
+                NumberState::Exp if matches!(ch, '-' | '+') => NumberState::ExpSign,
+                NumberState::Exp => {
+                    state = NumberState::ExpSign;
+                    continue;
+                }
+                NumberState::ExpSign | NumberState::ExpDigits if ch.is_ascii_digit() => {
+                    NumberState::ExpDigits
+                }
+                NumberState::ExpSign => {
+                    return Err(self.number_error(start, "expected digits in the exponent"));
+                }
+                NumberState::HexDigits | NumberState::AfterFrac | NumberState::ExpDigits => break,
+            };
+            text.push(ch);
             self.next();
         }
+        Ok(self.new_literal(start, TokenType::Number, text))
+    }
 
-        return name;
+    // Skips the rest of a malformed number, so that it is one error.
+    fn number_error(&mut self, location: Location, message: &str) -> Token {
+        while self.ch.is_ascii_alphanumeric() || matches!(self.ch, '.' | '+' | '-') {
+            self.next();
+        }
+        self.error(location, ErrorCode::InvalidNumber, message)
     }
 
-    fn next_with_space(&mut self) -> Option<char> {
-        let res = self.source.next();
-        self.ch = res.unwrap_or('\0');
-        self.location.next_column();
-        res
+    // A name, from where `name` leaves off. After a sign it is a number,
+    // as in `-Infinity`.
+    fn ident(&mut self, start: Location, mut name: String, number: bool) -> Result<Token, Token> {
+        loop {
+            if self.suspended() {
+                let partial = Partial::Ident {
+                    start,
+                    name,
+                    number,
+                };
+                return Err(self.suspend(Some(partial), self.prev));
+            }
+            if !(self.is_letter() || self.is_ident_part()) {
+                break;
+            }
+            if self.ch == '\\' {
+                // JSON5 identifiers may spell any character as \uXXXX.
+                let location = self.location;
+                let before = self.prev;
+                self.next();
+                let escaped = match self.ch {
+                    'u' => self.unicode_escape(location),
+                    _ => Err(self.error(
+                        location,
+                        ErrorCode::InvalidEscape,
+                        "expected \\u escape in identifier",
+                    )),
+                };
+                if self.source.suspended() {
+                    let partial = Partial::Ident {
+                        start,
+                        name,
+                        number,
+                    };
+                    return Err(self.suspend(Some(partial), before));
+                }
+                name.push(escaped?);
+            } else {
+                name.push(self.ch);
+            }
+            self.next();
+        }
+        let token_type = match TokenType::lookup_ident(&name) {
+            _ if number => TokenType::Number,
+            TokenType::Ident if self.is_nan_infinity(&name) => TokenType::Number,
+            token_type => token_type,
+        };
+        Ok(self.new_literal(start, token_type, name))
     }
 
-    fn error(&self) -> Token {
-        return self.new_token(TokenType::ILLEGAL);
+    fn is_nan_infinity(&self, ident: &str) -> bool {
+        self.options.allow_nan_infinity && matches!(ident, "NaN" | "Infinity")
     }
 
     fn is_letter(&self) -> bool {
-        matches!(self.ch, 'a'..='z' | 'A'..='Z' | '_')
+        if self.options.json5_extensions {
+            // ECMAScript IdentifierStart
+            self.ch.is_alphabetic() || matches!(self.ch, '$' | '_' | '\\')
+        } else {
+            matches!(self.ch, 'a'..='z' | 'A'..='Z' | '_')
+        }
+    }
+    fn is_ident_part(&self) -> bool {
+        if self.options.json5_extensions {
+            self.ch.is_alphanumeric() || matches!(self.ch, '\u{200c}' | '\u{200d}')
+        } else {
+            self.ch.is_ascii_digit()
+        }
     }
     fn is_digit(&self, zero: bool) -> bool {
         if zero {
@@ -207,25 +800,42 @@ where
             matches!(self.ch, '1'..='9' | '-')
         }
     }
+
+    fn peek(&mut self) -> Option<char> {
+        *self.peeked.get_or_insert_with(|| self.source.next())
+    }
 }
 
 impl<T> Iterator for Lexer<T>
 where
-    T: Iterator<Item = char> + Clone,
+    T: Source,
 {
     type Item = char;
     fn next(&mut self) -> Option<Self::Item> {
-        let res = self.source.next();
+        if self.ch_len > 0 {
+            self.prev = Some((self.location, self.ch));
+        }
+        // `\r\n` counts as a single line break.
+        let prev = self.ch;
+        let res = match self.peeked.take() {
+            Some(peeked) => peeked,
+            None => self.source.next(),
+        };
         self.ch = res.unwrap_or('\0');
-        self.ignore_space();
-        self.location.next_column();
+        self.location.offset += self.ch_len;
+        self.ch_len = res.map_or(0, char::len_utf8);
+        if prev == '\n' || (prev == '\r' && self.ch != '\n') {
+            self.location.next_line();
+        } else {
+            self.location.next_column();
+        }
         res
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::token::TokenType;
+    use crate::{error::ErrorCode, token::TokenType};
 
     use super::*;
 
@@ -254,6 +864,12 @@ mod tests {
             "-123.123",
             "123.123e-123",
             "123.123e+123",
+            "1e300",
+            "1E-7",
+            "1e5",
+            "-0.0e0",
+            "0",
+            "-0",
         ];
 
         for input in inputs {
@@ -266,12 +882,44 @@ mod tests {
     #[test]
     fn test_parse_invalid_number() {
         let inputs = vec![
-            "+123", "01", "-0.e", "-0.0e0", "-0.0ee-0", ".123", "123e", "123.e",
+            ("+123", "unexpected character '+'"),
+            ("01", "leading zeros are not allowed"),
+            ("-012.5", "leading zeros are not allowed"),
+            ("-", "expected a digit after '-'"),
+            ("-.5", "expected a digit after '-'"),
+            ("-0.e", "expected digits after the decimal point"),
+            ("1.", "expected digits after the decimal point"),
+            ("-0.0ee-0", "expected digits in the exponent"),
+            (".123", "unexpected character '.'"),
+            ("123e", "expected digits in the exponent"),
+            ("123e+", "expected digits in the exponent"),
+            ("123.e", "expected digits after the decimal point"),
         ];
 
-        for input in inputs {
+        for (input, message) in inputs {
             let mut lex = Lexer::<Chars>::new(input);
             let token = lex.next_token();
+            assert_eq!(TokenType::ILLEGAL, token.token_type, "{}", input);
+            assert_eq!(Some(message), token.value.as_deref(), "{}", input);
+            assert_eq!(1, token.location.column);
+            if input.starts_with(['+', '.']) {
+                continue;
+            }
+            assert_eq!(Some(ErrorCode::InvalidNumber), token.error_code);
+            // the whole number is one error
+            assert_eq!(TokenType::EOF, lex.next_token().token_type, "{}", input);
+        }
+
+        let inputs = vec![
+            ("0x", "expected hexadecimal digits after '0x'"),
+            (".", "expected digits after the decimal point"),
+            ("+.e1", "expected digits after the decimal point"),
+            ("00.5", "leading zeros are not allowed"),
+        ];
+        for (input, message) in inputs {
+            let mut lex = Lexer::<Chars>::with_options(input, ParserOptions::json5());
+            let token = lex.next_token();
+            assert_eq!(Some(message), token.value.as_deref(), "{}", input);
         }
     }
 
@@ -295,6 +943,239 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_token_location() {
+        let input = "[\r\n  1,\n  \"a\"\n]";
+
+        let mut lex = Lexer::<Chars>::new(input);
+        let expected = vec![(1, 1), (2, 3), (2, 4), (3, 3), (4, 1), (4, 2)];
+        for (line, column) in expected {
+            let t = lex.next_token();
+            assert_eq!((line, column), (t.location.line, t.location.column));
+        }
+    }
+
+    #[test]
+    fn test_token_span() {
+        let input = "{\r\n  \"k\u{e9}y\": [\"\u{1f600}\\n\", -1.5]\r\n}";
+
+        let expected = vec![
+            ("{", (1, 1), (1, 2)),
+            ("\"k\u{e9}y\"", (2, 3), (2, 8)),
+            (":", (2, 8), (2, 9)),
+            ("[", (2, 10), (2, 11)),
+            ("\"\u{1f600}\\n\"", (2, 11), (2, 16)),
+            (",", (2, 16), (2, 17)),
+            ("-1.5", (2, 18), (2, 22)),
+            ("]", (2, 22), (2, 23)),
+            ("}", (3, 1), (3, 2)),
+            ("", (3, 2), (3, 2)),
+        ];
+        let mut lex = Lexer::<Chars>::new(input);
+        for (text, start, end) in expected {
+            let t = lex.next_token();
+            let span = t.span();
+            assert_eq!(text, &input[span.range()]);
+            assert_eq!(start, (span.start.line, span.start.column), "{}", text);
+            assert_eq!(end, (span.end.line, span.end.column), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        let mut lex = Lexer::<Chars>::new("[1,\n  \"abc\\\"");
+        lex.next_token();
+        lex.next_token();
+        lex.next_token();
+        let t = lex.next_token();
+        assert_eq!(TokenType::ILLEGAL, t.token_type);
+        assert_eq!(Some(ErrorCode::UnterminatedString), t.error_code);
+        assert_eq!((2, 3), (t.location.line, t.location.column));
+        assert_eq!(TokenType::EOF, lex.next_token().token_type);
+    }
+
+    #[test]
+    fn test_control_character() {
+        let inputs = vec![
+            ("[\"a\tb\", 1]", 4, "U+0009"),
+            ("[\"a\nb\", 1]", 4, "U+000A"),
+            ("[\"\u{1}\", 1]", 3, "U+0001"),
+            ("[\"ab\u{0}\", 1]", 5, "U+0000"),
+            ("[\"\u{1f}\", 1]", 3, "U+001F"),
+        ];
+        for (input, column, code_point) in inputs {
+            let mut lex = Lexer::<Chars>::new(input);
+            lex.next_token();
+            let t = lex.next_token();
+            assert_eq!(
+                Some(ErrorCode::ControlCharacter),
+                t.error_code,
+                "{:?}",
+                input
+            );
+            assert_eq!(column, t.location.column, "{:?}", input);
+            assert_eq!(
+                Some(format!(
+                    "unescaped control character {} in string",
+                    code_point
+                )),
+                t.value
+            );
+            assert_eq!(TokenType::Comma, lex.next_token().token_type);
+        }
+
+        // JSON5 only requires line terminators to be escaped
+        let mut lex = Lexer::<Chars>::with_options("'a\tb\u{0}\u{7f}'", ParserOptions::json5());
+        assert_eq!(Some("a\tb\u{0}\u{7f}".to_string()), lex.next_token().value);
+        let mut lex = Lexer::<Chars>::with_options("'a\r\nb'", ParserOptions::json5());
+        assert_eq!(
+            Some(ErrorCode::ControlCharacter),
+            lex.next_token().error_code
+        );
+
+        // NUL outside strings is not the end of the input
+        let mut lex = Lexer::<Chars>::new("1\u{0}");
+        assert_eq!(TokenType::Number, lex.next_token().token_type);
+        let t = lex.next_token();
+        assert_eq!(Some("unexpected character '\\0'".to_string()), t.value);
+        assert_eq!(TokenType::EOF, lex.next_token().token_type);
+    }
+
+    #[test]
+    fn test_string_escape() {
+        let inputs = vec![
+            (r#""\"\\\/""#, "\"\\/"),
+            (r#""a\bb\fc\nd\re\tf""#, "a\u{8}b\u{c}c\nd\re\tf"),
+            (r#""\u0041\u00e9\u3042""#, "A\u{e9}\u{3042}"),
+            (r#""\ud83d\ude00""#, "\u{1f600}"),
+            (r#""\uD83D\uDE00!""#, "\u{1f600}!"),
+        ];
+
+        for (input, expected) in inputs {
+            let mut lex = Lexer::<Chars>::new(input);
+            let t = lex.next_token();
+            assert_eq!(TokenType::String, t.token_type, "{}", input);
+            assert_eq!(expected, t.value.unwrap());
+            assert_eq!(TokenType::EOF, lex.next_token().token_type);
+        }
+    }
+
+    #[test]
+    fn test_invalid_string_escape() {
+        let inputs = vec![
+            (r#"["a\x", 1]"#, 4),
+            (r#"["\u12G4", 1]"#, 3),
+            (r#"["\u12", 1]"#, 3),
+            (r#"["ab\ud83d", 1]"#, 5),
+            (r#"["\ud83dx", 1]"#, 3),
+            (r#"["\ud83d\u0041", 1]"#, 9),
+            (r#"["\ud83d\"", 1]"#, 3),
+            (r#"["\ud83d\\", 1]"#, 3),
+            (r#"["\ude00", 1]"#, 3),
+        ];
+
+        for (input, column) in inputs {
+            let mut lex = Lexer::<Chars>::new(input);
+            assert_eq!(TokenType::LeftBracket, lex.next_token().token_type);
+            let t = lex.next_token();
+            assert_eq!(TokenType::ILLEGAL, t.token_type, "{}", input);
+            assert_eq!(column, t.location.column, "{}", input);
+
+            // lexing resumes after the broken string
+            assert_eq!(TokenType::Comma, lex.next_token().token_type, "{}", input);
+            assert_eq!(TokenType::Number, lex.next_token().token_type, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_comment_token() {
+        let input = "// head\r\n[1, /* a\n * b\n */ 2 // tail\n]/**/";
+        let options = ParserOptions::jsonc().emit_comments(true);
+
+        let expected = vec![
+            (TokenType::Comment, Some("// head"), (1, 1)),
+            (TokenType::LeftBracket, None, (2, 1)),
+            (TokenType::Number, Some("1"), (2, 2)),
+            (TokenType::Comma, None, (2, 3)),
+            (TokenType::Comment, Some("/* a\n * b\n */"), (2, 5)),
+            (TokenType::Number, Some("2"), (4, 5)),
+            (TokenType::Comment, Some("// tail"), (4, 7)),
+            (TokenType::RightBracket, None, (5, 1)),
+            (TokenType::Comment, Some("/**/"), (5, 2)),
+            (TokenType::EOF, None, (5, 6)),
+        ];
+
+        let mut lex = Lexer::<Chars>::with_options(input, options);
+        for (token_type, value, (line, column)) in expected {
+            let t = lex.next_token();
+            assert_eq!(
+                (token_type, value.map(String::from), line, column),
+                (t.token_type, t.value, t.location.line, t.location.column)
+            );
+        }
+
+        // without emit_comments they are skipped like whitespace
+        let mut lex = Lexer::<Chars>::with_options(input, ParserOptions::jsonc());
+        let t = lex.next_token();
+        assert_eq!(
+            (TokenType::LeftBracket, 2, 1),
+            (t.token_type, t.location.line, t.location.column)
+        );
+    }
+
+    #[test]
+    fn test_invalid_comment() {
+        let inputs = vec![
+            ("/* open", (1, 1)),
+            ("[\n  /* a */ /* b", (2, 11)),
+            ("/ 1", (1, 1)),
+        ];
+        for (input, (line, column)) in inputs {
+            let mut lex = Lexer::<Chars>::with_options(input, ParserOptions::jsonc());
+            let mut t = lex.next_token();
+            while t.token_type == TokenType::LeftBracket {
+                t = lex.next_token();
+            }
+            assert_eq!(TokenType::ILLEGAL, t.token_type, "{}", input);
+            assert_eq!((line, column), (t.location.line, t.location.column));
+        }
+    }
+
+    #[test]
+    fn test_byte_lexer() {
+        let input = r#"{"k\u00e9y": ["日本語", -1.5e3, true, null]}"#;
+        let mut chars = Lexer::<Chars>::new(input);
+        let mut bytes = Lexer::from_slice(input.as_bytes(), ParserOptions::default());
+        loop {
+            let (c, b) = (chars.next_token(), bytes.next_token());
+            assert_eq!((&c.token_type, &c.value), (&b.token_type, &b.value));
+            if c.token_type == TokenType::EOF {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let inputs: Vec<(&[u8], &str)> = vec![
+            (b"[\"ab\xffc\"]", "invalid UTF-8 at byte offset 4"),
+            (b"[\"\xc3\xa9\xe3\x81\"]", "invalid UTF-8 at byte offset 4"),
+            (b"[1, \xf0]", "invalid UTF-8 at byte offset 4"),
+            (b"[\"\\u00\x80", "invalid UTF-8 at byte offset 6"),
+        ];
+
+        for (input, message) in inputs {
+            let mut lex = Lexer::from_slice(input, ParserOptions::default());
+            let mut t = lex.next_token();
+            while t.token_type != TokenType::ILLEGAL && t.token_type != TokenType::EOF {
+                t = lex.next_token();
+            }
+            assert_eq!(Some(message.to_string()), t.value);
+            // reported once, then the input simply ends
+            assert_eq!(TokenType::EOF, lex.next_token().token_type);
+        }
+    }
+
     #[test]
     fn test_lexer() {
         let input = r#"
@@ -359,7 +1240,7 @@ mod tests {
         let mut lex = Lexer::<Chars>::new(input);
         println!("{:?}", lex);
 
-        for (i, e) in expected.iter().enumerate() {
+        for e in expected.iter() {
             let t = lex.next_token();
             println!("{:?} {:?}\n", t, e);
             assert_eq!(e, &(t.token_type, t.value));
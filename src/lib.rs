@@ -1,5 +1,52 @@
+pub mod borrowed;
+pub mod diagnostic;
 pub mod error;
+pub mod event;
 pub mod lexer;
+pub mod line_index;
+pub mod ndjson;
+pub mod number;
+pub mod options;
 pub mod parser;
+pub mod push;
+pub mod ser;
+pub mod source;
+pub mod spanned;
 pub mod token;
 pub mod value;
+pub mod writer;
+
+pub use crate::ser::{to_string, to_string_pretty, to_writer};
+
+use std::io::Read;
+
+use crate::{error::JSONError, options::ParserOptions, parser::Parser, value::Value};
+
+/// Parses `source` as a single strict RFC 8259 document, failing on the
+/// first diagnostic reported by the parser.
+pub fn from_str(source: &str) -> Result<Value, JSONError> {
+    from_str_with_options(source, ParserOptions::default())
+}
+
+/// Parses UTF-8 bytes without converting them to a `&str` first.
+pub fn from_slice(source: &[u8]) -> Result<Value, JSONError> {
+    first_error(Parser::from_slice(source, ParserOptions::default()).parse_all())
+}
+
+/// Parses a document read incrementally from `reader`; a failed read is
+/// reported as `JSONError::IOError`.
+pub fn from_reader<R: Read>(reader: R) -> Result<Value, JSONError> {
+    first_error(Parser::from_reader(reader, ParserOptions::default()).parse_all())
+}
+
+/// Like `from_str`, in the dialect selected by `options`.
+pub fn from_str_with_options(source: &str, options: ParserOptions) -> Result<Value, JSONError> {
+    first_error(Parser::with_options(source, options).parse_all())
+}
+
+fn first_error<V>((value, errors): (V, Vec<JSONError>)) -> Result<V, JSONError> {
+    match errors.into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(value),
+    }
+}
@@ -0,0 +1,89 @@
+//! Line and column numbers for byte offsets, such as `Location::offset`.
+
+/// Maps byte offsets in a text to 1-based line and column numbers, with
+/// columns counted in UTF-8 bytes or in UTF-16 code units (as editors using
+/// the Language Server Protocol expect).
+///
+/// Lines end at `\n`, `\r\n` or a lone `\r`, like in the lexer.
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    text: &'a str,
+    // byte offset of the first character of every line
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(text: &'a str) -> Self {
+        let bytes = text.as_bytes();
+        let mut line_starts = vec![0];
+        for (i, &b) in bytes.iter().enumerate() {
+            let line_break = b == b'\n' || (b == b'\r' && bytes.get(i + 1) != Some(&b'\n'));
+            if line_break {
+                line_starts.push(i + 1);
+            }
+        }
+        LineIndex { text, line_starts }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Line and UTF-8 column of `offset`. Offsets past the end of the text
+    /// are clamped to it.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let (line, start, offset) = self.line_of(offset);
+        (line, offset - start + 1)
+    }
+
+    /// Line and UTF-16 column of `offset`. An offset inside a character
+    /// counts as the start of that character.
+    pub fn line_col_utf16(&self, offset: usize) -> (usize, usize) {
+        let (line, start, offset) = self.line_of(offset);
+        let column: usize = self.text[start..]
+            .char_indices()
+            .take_while(|&(i, ch)| start + i + ch.len_utf8() <= offset)
+            .map(|(_, ch)| ch.len_utf16())
+            .sum();
+        (line, column + 1)
+    }
+
+    // The line containing `offset`, the offset where it starts and the
+    // clamped `offset`.
+    fn line_of(&self, offset: usize) -> (usize, usize, usize) {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        (line, self.line_starts[line - 1], offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_index() {
+        let text = "ab\r\n\u{e9}\u{1f600}x\ry\n";
+        let index = LineIndex::new(text);
+        assert_eq!(4, index.line_count());
+
+        let inputs = vec![
+            (0, (1, 1), (1, 1)),
+            (2, (1, 3), (1, 3)),
+            (3, (1, 4), (1, 4)),
+            (4, (2, 1), (2, 1)),
+            (6, (2, 3), (2, 2)),
+            (10, (2, 7), (2, 4)),
+            (11, (2, 8), (2, 5)),
+            (12, (3, 1), (3, 1)),
+            (14, (4, 1), (4, 1)),
+            (100, (4, 1), (4, 1)),
+        ];
+        for (offset, utf8, utf16) in inputs {
+            assert_eq!(utf8, index.line_col(offset), "{}", offset);
+            assert_eq!(utf16, index.line_col_utf16(offset), "{}", offset);
+        }
+        // inside the emoji
+        assert_eq!((2, 2), index.line_col_utf16(8));
+    }
+}
@@ -0,0 +1,182 @@
+//! Newline-delimited JSON (NDJSON, also known as JSON Lines): one document
+//! per line.
+
+use std::io::{BufRead, Write};
+
+use crate::{
+    error::JSONError, first_error, options::ParserOptions, parser::Parser, ser::write_value,
+    token::Location, value::Value,
+};
+
+/// Parses each line of `R` as a document of its own. Blank lines are
+/// skipped. An invalid line is reported with its location in the whole
+/// input and does not stop the lines after it from being read; a failed
+/// read ends the iteration.
+#[derive(Debug)]
+pub struct NdjsonReader<R: BufRead> {
+    reader: R,
+    options: ParserOptions,
+    buf: Vec<u8>,
+    // where the next line starts
+    start: Location,
+    // the number of the last line read
+    line: usize,
+    done: bool,
+}
+
+impl<R: BufRead> NdjsonReader<R> {
+    pub fn new(reader: R) -> Self {
+        NdjsonReader::with_options(reader, ParserOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParserOptions) -> Self {
+        NdjsonReader {
+            reader,
+            options,
+            buf: Vec::new(),
+            start: Location::new(1, 1),
+            line: 0,
+            done: false,
+        }
+    }
+
+    /// The 1-based number of the line the last item came from.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl<R: BufRead> Iterator for NdjsonReader<R> {
+    type Item = Result<Value, JSONError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.buf.clear();
+            let n = match self.reader.read_until(b'\n', &mut self.buf) {
+                Ok(n) => n,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(JSONError::IOError(e)));
+                }
+            };
+            if n == 0 {
+                self.done = true;
+                break;
+            }
+            let start = self.start;
+            self.line = start.line;
+            self.start = Location {
+                line: start.line + 1,
+                column: 1,
+                offset: start.offset + n,
+            };
+
+            if self.buf.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            let parser = Parser::from_slice(&self.buf, self.options);
+            return Some(first_error(parser.parse_all()).map_err(|e| e.relative_to(start)));
+        }
+        None
+    }
+}
+
+/// Writes values as compact JSON, one per line.
+#[derive(Debug)]
+pub struct NdjsonWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    pub fn new(writer: W) -> Self {
+        NdjsonWriter { writer }
+    }
+
+    pub fn write(&mut self, value: &Value) -> Result<(), JSONError> {
+        // Compact output has no line breaks; those in strings are escaped.
+        write_value(&mut self.writer, value)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), JSONError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Flushes and gives back the underlying writer.
+    pub fn into_inner(mut self) -> Result<W, JSONError> {
+        self.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+
+    #[test]
+    fn test_ndjson_reader() {
+        let src = "{\"a\": 1}\n\n  [1, 2]\r\n{\"b\" 2}\n\"x\"\n  \n{\"c\": [1,]}\nnull";
+        let mut reader = NdjsonReader::new(src.as_bytes());
+        let mut found = vec![];
+        while let Some(item) = reader.next() {
+            let item = item.map(|v| v.to_string()).map_err(|e| {
+                let location = e.location().unwrap();
+                (e.code(), location.line, location.column, location.offset)
+            });
+            found.push((reader.line(), item));
+        }
+        assert_eq!(
+            vec![
+                (1, Ok(r#"{"a":1}"#.to_string())),
+                (3, Ok("[1,2]".to_string())),
+                (4, Err((ErrorCode::UnexpectedToken, 4, 6, 25))),
+                (5, Ok("\"x\"".to_string())),
+                (7, Err((ErrorCode::TrailingComma, 7, 9, 43))),
+                (8, Ok("null".to_string())),
+            ],
+            found
+        );
+        let e = NdjsonReader::new(src.as_bytes())
+            .find_map(Result::err)
+            .unwrap();
+        assert_eq!("2", &src[e.span().unwrap().range()]);
+
+        let options = ParserOptions::lenient();
+        let values: Vec<_> = NdjsonReader::with_options("[1,]\n{a: 'b'}".as_bytes(), options)
+            .map(|v| v.unwrap().to_string())
+            .collect();
+        assert_eq!(vec!["[1]", r#"{"a":"b"}"#], values);
+
+        assert_eq!(0, NdjsonReader::new("".as_bytes()).count());
+
+        let src = b"[1]\n\"a\xff\"\n";
+        let e = NdjsonReader::new(&src[..]).find_map(Result::err).unwrap();
+        assert_eq!(ErrorCode::InvalidUtf8, e.code());
+        assert_eq!(6, e.span().unwrap().start.offset);
+        assert_eq!("invalid UTF-8 at byte offset 6", e.message());
+    }
+
+    #[test]
+    fn test_ndjson_round_trip() {
+        let values = vec![
+            crate::from_str(r#"{"msg": "two\nlines", "n": [1, 2.5]}"#).unwrap(),
+            Value::Null,
+            Value::String("\u{2028}".to_string()),
+        ];
+        let mut writer = NdjsonWriter::new(Vec::new());
+        for value in &values {
+            writer.write(value).unwrap();
+        }
+        let out = writer.into_inner().unwrap();
+        assert_eq!(
+            "{\"msg\":\"two\\nlines\",\"n\":[1,2.5]}\nnull\n\"\u{2028}\"\n",
+            String::from_utf8(out.clone()).unwrap()
+        );
+
+        let read: Vec<_> = NdjsonReader::new(&out[..]).map(Result::unwrap).collect();
+        assert_eq!(values, read);
+    }
+}
@@ -0,0 +1,551 @@
+use std::{
+    fmt::{Display, Formatter},
+    hash::{Hash, Hasher},
+    str::{Chars, FromStr},
+};
+
+use crate::{lexer::Lexer, options::ParserOptions, token::TokenType};
+
+// RFC 8259 numbers are kept as the narrowest of the three representations
+// that can hold them: u64 for non-negative integers, i64 for negative
+// integers and f64 for everything else. In lossless mode the text from the
+// lexer is kept as is and only converted on access.
+#[derive(Debug, Clone)]
+enum N {
+    PosInt(u64),
+    NegInt(i64),
+    Float(f64),
+    Raw(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Number {
+    n: N,
+}
+
+impl Number {
+    /// Returns `None` for NaN and infinities, which JSON cannot represent.
+    pub fn from_f64(f: f64) -> Option<Number> {
+        if f.is_finite() {
+            Some(Number { n: N::Float(f) })
+        } else {
+            None
+        }
+    }
+
+    /// Keeps `s` verbatim so that it is written back exactly as it was read,
+    /// whatever its precision or magnitude. `s` must be an RFC 8259 number.
+    pub fn from_raw(s: &str) -> Result<Number, ParseNumberError> {
+        // The lexer's number rules decide, so nothing is kept that could not
+        // be read back.
+        let mut lexer = Lexer::<Chars>::new(s);
+        let token = lexer.next_token();
+        let valid = token.token_type == TokenType::Number
+            && token.value.as_deref() == Some(s)
+            && lexer.next_token().token_type == TokenType::EOF
+            // `-` and a name, as in `-Infinity`, is a number token too, left
+            // for the parser to reject
+            && s.trim_start_matches('-').starts_with(|c: char| c.is_ascii_digit());
+        if valid {
+            Ok(Number {
+                n: N::Raw(s.to_string()),
+            })
+        } else {
+            Err(ParseNumberError(s.to_string()))
+        }
+    }
+
+    // NaN and the infinities, for dialects that allow them.
+    pub(crate) fn from_f64_unchecked(f: f64) -> Number {
+        Number { n: N::Float(f) }
+    }
+
+    pub fn is_finite(&self) -> bool {
+        match &self.n {
+            N::Float(f) => f.is_finite(),
+            _ => true,
+        }
+    }
+
+    /// The original text of a number parsed in lossless mode.
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.n {
+            N::Raw(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn is_u64(&self) -> bool {
+        self.as_u64().is_some()
+    }
+    pub fn is_i64(&self) -> bool {
+        self.as_i64().is_some()
+    }
+    pub fn is_f64(&self) -> bool {
+        match &self.n {
+            N::Float(_) => true,
+            N::Raw(s) => !is_integer(s),
+            _ => false,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match &self.n {
+            N::PosInt(u) => Some(*u),
+            N::NegInt(_) | N::Float(_) => None,
+            N::Raw(s) => s.parse().ok(),
+        }
+    }
+    pub fn as_i64(&self) -> Option<i64> {
+        match &self.n {
+            N::PosInt(u) => i64::try_from(*u).ok(),
+            N::NegInt(i) => Some(*i),
+            N::Float(_) => None,
+            N::Raw(s) => s.parse().ok(),
+        }
+    }
+    pub fn as_f64(&self) -> Option<f64> {
+        match &self.n {
+            N::PosInt(u) => Some(*u as f64),
+            N::NegInt(i) => Some(*i as f64),
+            N::Float(f) => Some(*f),
+            N::Raw(s) => s.parse::<f64>().ok().filter(|f| f.is_finite()),
+        }
+    }
+    pub fn as_u128(&self) -> Option<u128> {
+        match &self.n {
+            N::PosInt(u) => Some(*u as u128),
+            N::NegInt(_) | N::Float(_) => None,
+            N::Raw(s) => s.parse().ok(),
+        }
+    }
+    pub fn as_i128(&self) -> Option<i128> {
+        match &self.n {
+            N::PosInt(u) => Some(*u as i128),
+            N::NegInt(i) => Some(*i as i128),
+            N::Float(_) => None,
+            N::Raw(s) => s.parse().ok(),
+        }
+    }
+
+    /// Splits the number into `(mantissa, exponent)` such that the value is
+    /// exactly `mantissa * 10^exponent`, or `None` if the digits do not fit
+    /// in an i128.
+    pub fn as_decimal(&self) -> Option<(i128, i32)> {
+        let text = self.to_string();
+        let (significand, exponent) = match text.find(['e', 'E']) {
+            Some(i) => (&text[..i], text[i + 1..].parse::<i32>().ok()?),
+            None => (&text[..], 0),
+        };
+        let (int, frac) = significand.split_once('.').unwrap_or((significand, ""));
+        let frac = frac.trim_end_matches('0');
+        let mantissa = format!("{}{}", int, frac).parse::<i128>().ok()?;
+        Some((mantissa, exponent.checked_sub(frac.len() as i32)?))
+    }
+}
+
+// Rewrites a JSON5 number literal in JSON syntax: drops a leading `+`,
+// converts hexadecimal to decimal and completes `.5` and `5.` with zeros.
+// NaN and Infinity keep their names. Unknown input is returned unchanged.
+pub(crate) fn normalize_json5(s: &str) -> String {
+    let (sign, body) = match s.as_bytes().first() {
+        Some(b'+') => ("", &s[1..]),
+        Some(b'-') => ("-", &s[1..]),
+        _ => ("", s),
+    };
+    if body == "NaN" {
+        return body.to_string();
+    }
+    if body == "Infinity" {
+        return format!("{}{}", sign, body);
+    }
+    if let Some(hex) = body.strip_prefix("0x").or_else(|| body.strip_prefix("0X")) {
+        return match u128::from_str_radix(hex, 16) {
+            Ok(n) => format!("{}{}", sign, n),
+            Err(_) => s.to_string(),
+        };
+    }
+
+    let (significand, exponent) = match body.find(['e', 'E']) {
+        Some(i) => body.split_at(i),
+        None => (body, ""),
+    };
+    let mut significand = significand.to_string();
+    if significand.starts_with('.') {
+        significand.insert(0, '0');
+    }
+    if significand.ends_with('.') {
+        significand.push('0');
+    }
+    format!("{}{}{}", sign, significand, exponent)
+}
+
+// The number a lexer `Number` token stands for, in the dialect and number
+// representation selected by `options`.
+pub(crate) fn from_token(text: &str, options: &ParserOptions) -> Result<Number, ParseNumberError> {
+    let normalized;
+    let text = if options.json5_extensions {
+        normalized = normalize_json5(text);
+        normalized.as_str()
+    } else {
+        text
+    };
+    if options.allow_nan_infinity && matches!(text, "NaN" | "Infinity" | "-Infinity") {
+        Ok(Number::from_f64_unchecked(text.parse().unwrap()))
+    } else if options.lossless_numbers {
+        Number::from_raw(text)
+    } else {
+        text.parse::<Number>()
+    }
+}
+
+fn is_integer(s: &str) -> bool {
+    !s.contains(['.', 'e', 'E'])
+}
+
+// The exact value of a decimal number as `(negative, digits, exponent)`,
+// meaning `digits * 10^exponent` with no leading or trailing zeros in
+// `digits`, so that equal values have equal forms whatever their notation.
+// `None` for exponents beyond i64.
+fn canonical(s: &str) -> Option<(bool, String, i64)> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let (significand, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], s[i + 1..].parse::<i64>().ok()?),
+        None => (s, 0),
+    };
+    let (int, frac) = significand.split_once('.').unwrap_or((significand, ""));
+    let digits = format!("{}{}", int, frac);
+    let trimmed = digits.trim_end_matches('0');
+    let exponent = exponent
+        .checked_sub(frac.len() as i64)?
+        .checked_add((digits.len() - trimmed.len()) as i64)?;
+    let trimmed = trimmed.trim_start_matches('0');
+    if trimmed.is_empty() {
+        return Some((false, String::new(), 0));
+    }
+    Some((negative, trimmed.to_string(), exponent))
+}
+
+impl Number {
+    // Integers and lossless numbers in canonical form; floats have none.
+    fn exact(&self) -> Option<(bool, String, i64)> {
+        match &self.n {
+            N::PosInt(u) => canonical(&u.to_string()),
+            N::NegInt(i) => canonical(&i.to_string()),
+            N::Float(_) => None,
+            N::Raw(s) => canonical(s),
+        }
+    }
+}
+
+// Lossless numbers are equal to integers and to each other by value, so
+// `1.0` equals `1`; like integers, they never equal a float.
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.n, &other.n) {
+            (N::PosInt(a), N::PosInt(b)) => a == b,
+            (N::NegInt(a), N::NegInt(b)) => a == b,
+            (N::Float(a), N::Float(b)) => a == b || (a.is_nan() && b.is_nan()),
+            (N::Float(_), _) | (_, N::Float(_)) => false,
+            (N::Raw(a), N::Raw(b)) if a == b => true,
+            _ => match (self.exact(), other.exact()) {
+                (Some(a), Some(b)) => a == b,
+                _ => false,
+            },
+        }
+    }
+}
+
+// NaN is equal to itself above, so equality is reflexive.
+impl Eq for Number {}
+
+impl Hash for Number {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match &self.n {
+            N::PosInt(u) => u.hash(state),
+            N::NegInt(i) => i.hash(state),
+            // Lossless numbers that equal an integer must hash like it.
+            N::Raw(s) => match self.exact() {
+                // zero, whose canonical form has no digits
+                Some((_, digits, _)) if digits.is_empty() => 0u64.hash(state),
+                Some((negative, digits, exponent))
+                    if exponent >= 0 && digits.len() as i64 + exponent <= 20 =>
+                {
+                    let int = format!("{}{}", digits, "0".repeat(exponent as usize));
+                    match (
+                        negative,
+                        int.parse::<u64>(),
+                        format!("-{}", int).parse::<i64>(),
+                    ) {
+                        (false, Ok(u), _) => u.hash(state),
+                        (true, _, Ok(i)) => i.hash(state),
+                        _ => (negative, digits, exponent).hash(state),
+                    }
+                }
+                Some(exact) => exact.hash(state),
+                None => s.hash(state),
+            },
+            N::Float(f) => {
+                if *f == 0.0 {
+                    // 0.0 == -0.0, so they must hash the same.
+                    0.0f64.to_bits().hash(state)
+                } else if f.is_nan() {
+                    f64::NAN.to_bits().hash(state)
+                } else {
+                    f.to_bits().hash(state)
+                }
+            }
+        }
+    }
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.n {
+            N::PosInt(u) => write!(f, "{}", u),
+            N::NegInt(i) => write!(f, "{}", i),
+            // `{:?}` keeps the fraction of integral floats and switches to
+            // exponent notation for very large or small magnitudes.
+            N::Float(v) if v.is_nan() => write!(f, "NaN"),
+            N::Float(v) if v.is_infinite() => {
+                write!(f, "{}Infinity", if *v < 0.0 { "-" } else { "" })
+            }
+            N::Float(v) => write!(f, "{:?}", v),
+            N::Raw(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseNumberError(String);
+
+impl Display for ParseNumberError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid number '{}'", self.0)
+    }
+}
+
+impl FromStr for Number {
+    type Err = ParseNumberError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseNumberError(s.to_string());
+
+        if is_integer(s) {
+            if s.starts_with('-') {
+                if let Ok(i) = s.parse::<i64>() {
+                    return Ok(Number::from(i));
+                }
+            } else if let Ok(u) = s.parse::<u64>() {
+                return Ok(Number::from(u));
+            }
+        }
+
+        // Integers outside the 64-bit range fall back to f64 as well.
+        let f = s.parse::<f64>().map_err(|_| err())?;
+        Number::from_f64(f).ok_or_else(err)
+    }
+}
+
+impl From<u64> for Number {
+    fn from(u: u64) -> Self {
+        Number { n: N::PosInt(u) }
+    }
+}
+
+impl From<i64> for Number {
+    fn from(i: i64) -> Self {
+        if i < 0 {
+            Number { n: N::NegInt(i) }
+        } else {
+            Number {
+                n: N::PosInt(i as u64),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_number() {
+        let n = "123".parse::<Number>().unwrap();
+        assert_eq!(
+            (Some(123), Some(123), Some(123.0)),
+            (n.as_u64(), n.as_i64(), n.as_f64())
+        );
+
+        let n = "-1".parse::<Number>().unwrap();
+        assert_eq!(
+            (None, Some(-1), Some(-1.0)),
+            (n.as_u64(), n.as_i64(), n.as_f64())
+        );
+
+        let n = "-0".parse::<Number>().unwrap();
+        assert!(n.is_u64());
+
+        let n = "2.5".parse::<Number>().unwrap();
+        assert_eq!(
+            (None, None, Some(2.5)),
+            (n.as_u64(), n.as_i64(), n.as_f64())
+        );
+
+        let n = "1e10".parse::<Number>().unwrap();
+        assert!(n.is_f64());
+        assert_eq!(Some(1e10), n.as_f64());
+
+        let n = "18446744073709551616".parse::<Number>().unwrap();
+        assert!(n.is_f64());
+
+        assert!("1e400".parse::<Number>().is_err());
+        assert!("1e".parse::<Number>().is_err());
+    }
+
+    fn hash(n: &Number) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        n.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_raw_number() {
+        let inputs = vec![
+            "123456789012345678901234567890",
+            "-0.100000000000000000000000000001",
+            "1e400",
+            "1.50",
+        ];
+        for input in inputs {
+            assert_eq!(input, Number::from_raw(input).unwrap().to_string());
+        }
+
+        let n = Number::from_raw("170141183460469231731687303715884105727").unwrap();
+        assert_eq!(Some(i128::MAX), n.as_i128());
+        assert_eq!(None, n.as_i64());
+        assert!(!n.is_f64());
+
+        let n = Number::from_raw("340282366920938463463374607431768211455").unwrap();
+        assert_eq!(Some(u128::MAX), n.as_u128());
+        assert_eq!(None, n.as_i128());
+
+        let n = Number::from_raw("42").unwrap();
+        assert_eq!((Some(42), Some(42)), (n.as_u64(), n.as_i64()));
+
+        let equal = vec![
+            ("42", "4.2e1"),
+            ("1.0", "1"),
+            ("-0.50", "-5E-1"),
+            ("0.0", "-0"),
+            ("1e40", "10000000000000000000000000000000000000000"),
+        ];
+        for (a, b) in equal {
+            let (a, b) = (Number::from_raw(a).unwrap(), Number::from_raw(b).unwrap());
+            assert_eq!(a, b);
+            assert_eq!(hash(&a), hash(&b));
+        }
+        let integers = [
+            ("42.0", Number::from(42u64)),
+            ("-7", Number::from(-7i64)),
+            ("0", Number::from(0u64)),
+            ("-0", Number::from(0u64)),
+            ("0.0e5", Number::from(0u64)),
+        ];
+        for (raw, n) in integers {
+            let raw = Number::from_raw(raw).unwrap();
+            assert_eq!(raw, n);
+            assert_eq!(hash(&raw), hash(&n));
+        }
+        assert_ne!(
+            Number::from_raw("1.5").unwrap(),
+            Number::from_f64(1.5).unwrap()
+        );
+        assert_ne!(
+            Number::from_raw("0.1").unwrap(),
+            Number::from_raw("0.100000000000000000000000000001").unwrap()
+        );
+
+        let invalid = vec![
+            "1e",
+            "-Infinity",
+            "-a1",
+            "-e1",
+            "01",
+            "00",
+            "-01",
+            "1.",
+            "1.e5",
+            ".5",
+            "+1",
+            "0x1F",
+            " 1",
+            "1 ",
+            "1 2",
+            "",
+        ];
+        for input in invalid {
+            assert!(Number::from_raw(input).is_err(), "{:?}", input);
+        }
+        assert!(Number::from_raw("inf").is_err());
+    }
+
+    #[test]
+    fn test_as_decimal() {
+        let inputs = vec![
+            ("12.3450", (12345, -3)),
+            ("-0.5", (-5, -1)),
+            ("1.5e3", (15, 2)),
+            ("100", (100, 0)),
+            (
+                "123456789012345.123456789012345",
+                (123456789012345123456789012345, -15),
+            ),
+        ];
+        for (input, expected) in inputs {
+            assert_eq!(
+                Some(expected),
+                Number::from_raw(input).unwrap().as_decimal()
+            );
+        }
+        assert_eq!(Some((-7, 0)), Number::from(-7i64).as_decimal());
+    }
+
+    #[test]
+    fn test_normalize_json5() {
+        let inputs = vec![
+            ("+1", "1"),
+            ("-1", "-1"),
+            (".5", "0.5"),
+            ("-.5e3", "-0.5e3"),
+            ("5.", "5.0"),
+            ("+5.E-1", "5.0E-1"),
+            ("0x1F", "31"),
+            ("-0XfF", "-255"),
+            ("+Infinity", "Infinity"),
+            ("-Infinity", "-Infinity"),
+            ("-NaN", "NaN"),
+            ("0x", "0x"),
+        ];
+        for (input, expected) in inputs {
+            assert_eq!(expected, normalize_json5(input));
+        }
+    }
+
+    #[test]
+    fn test_display_number() {
+        let inputs = vec![
+            ("123", "123"),
+            ("-123", "-123"),
+            ("1.5", "1.5"),
+            ("1.0", "1.0"),
+            ("1e300", "1e300"),
+        ];
+        for (input, expected) in inputs {
+            assert_eq!(expected, input.parse::<Number>().unwrap().to_string());
+        }
+    }
+}
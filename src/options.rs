@@ -0,0 +1,130 @@
+/// Selects the JSON dialect accepted by `Lexer` and `Parser`.
+///
+/// `ParserOptions::default()` is strict RFC 8259; every extension has to be
+/// enabled explicitly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParserOptions {
+    pub(crate) allow_trailing_commas: bool,
+    pub(crate) allow_comments: bool,
+    pub(crate) emit_comments: bool,
+    pub(crate) allow_unquoted_keys: bool,
+    pub(crate) allow_single_quotes: bool,
+    pub(crate) allow_nan_infinity: bool,
+    pub(crate) json5_extensions: bool,
+    pub(crate) lossless_numbers: bool,
+    pub(crate) json_seq: bool,
+    pub(crate) max_depth: usize,
+}
+
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions {
+            allow_trailing_commas: false,
+            allow_comments: false,
+            emit_comments: false,
+            allow_unquoted_keys: false,
+            allow_single_quotes: false,
+            allow_nan_infinity: false,
+            json5_extensions: false,
+            lossless_numbers: false,
+            json_seq: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+}
+
+impl ParserOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Strict RFC 8259, same as `ParserOptions::default()`.
+    pub fn strict() -> Self {
+        Self::default()
+    }
+
+    /// JSON with Comments, as used by tsconfig.json and editor settings:
+    /// comments and trailing commas.
+    pub fn jsonc() -> Self {
+        Self::default()
+            .allow_comments(true)
+            .allow_trailing_commas(true)
+    }
+
+    /// Trailing commas, comments, unquoted keys, single quotes and
+    /// NaN/Infinity.
+    pub fn lenient() -> Self {
+        Self::default()
+            .allow_trailing_commas(true)
+            .allow_comments(true)
+            .allow_unquoted_keys(true)
+            .allow_single_quotes(true)
+            .allow_nan_infinity(true)
+    }
+
+    /// JSON5 (https://spec.json5.org): everything in `lenient()` plus the
+    /// JSON5-only syntax of `json5_extensions`.
+    pub fn json5() -> Self {
+        Self::lenient().json5_extensions(true)
+    }
+
+    /// `[1, 2,]` and `{"a": 1,}`.
+    pub fn allow_trailing_commas(mut self, enable: bool) -> Self {
+        self.allow_trailing_commas = enable;
+        self
+    }
+    /// `// line` and `/* block */` comments wherever whitespace is allowed.
+    pub fn allow_comments(mut self, enable: bool) -> Self {
+        self.allow_comments = enable;
+        self
+    }
+    /// Makes `Lexer::next_token` return comments as `TokenType::Comment`
+    /// tokens instead of skipping them. The parser ignores them either way.
+    pub fn emit_comments(mut self, enable: bool) -> Self {
+        self.emit_comments = enable;
+        self
+    }
+    /// `{key: 1}`, where the key is a bare identifier.
+    pub fn allow_unquoted_keys(mut self, enable: bool) -> Self {
+        self.allow_unquoted_keys = enable;
+        self
+    }
+    /// `'string'` in addition to `"string"`.
+    pub fn allow_single_quotes(mut self, enable: bool) -> Self {
+        self.allow_single_quotes = enable;
+        self
+    }
+    /// `NaN`, `Infinity` and `-Infinity` as numbers.
+    pub fn allow_nan_infinity(mut self, enable: bool) -> Self {
+        self.allow_nan_infinity = enable;
+        self
+    }
+    /// The parts of JSON5 not covered by the other options: hexadecimal
+    /// numbers, leading or trailing decimal points, an explicit `+`, line
+    /// continuations and `\v`, `\0`, `\xHH` escapes in strings, ECMAScript
+    /// identifiers (Unicode letters, `$`, `\uXXXX`) and Unicode whitespace.
+    pub fn json5_extensions(mut self, enable: bool) -> Self {
+        self.json5_extensions = enable;
+        self
+    }
+    /// Keeps numbers as the text the lexer read instead of converting them,
+    /// so that values beyond u64/i64/f64 survive a round trip unchanged.
+    pub fn lossless_numbers(mut self, enable: bool) -> Self {
+        self.lossless_numbers = enable;
+        self
+    }
+    /// RFC 7464 JSON text sequences: each document is preceded by U+001E
+    /// (RS). Only `Parser::documents` reads more than one document.
+    pub fn json_seq(mut self, enable: bool) -> Self {
+        self.json_seq = enable;
+        self
+    }
+    /// Maximum number of arrays and objects that may be nested inside each
+    /// other. Deeper input is rejected instead of exhausting the stack.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+}
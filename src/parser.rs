@@ -1,161 +1,502 @@
-use std::{collections::HashMap, error::Error, str::Chars};
+use std::{borrow::Cow, io::Read, str::Chars};
 
 use crate::{
-    error::JSONError,
-    lexer::{self, Lexer},
-    token::{Token, TokenType},
+    borrowed,
+    error::{ErrorCode, Expected, JSONError},
+    first_error,
+    lexer::Lexer,
+    number::{self, Number},
+    options::ParserOptions,
+    source::{ByteSource, ReadSource, Source},
+    spanned::{self, Spanned},
+    token::{Location, Span, Token, TokenType},
     value::Value,
 };
 
+pub use crate::options::DEFAULT_MAX_DEPTH;
+
 #[derive(Debug)]
-pub struct Parser<'a> {
-    lexer: Lexer<Chars<'a>>,
+pub struct Parser<T: Source> {
+    lexer: Lexer<T>,
 
     curr_tok: Token,
     peek_tok: Token,
+    // end of the token before `curr_tok`
+    prev_end: Location,
 
     errors: Vec<JSONError>,
+
+    options: ParserOptions,
+
+    depth: usize,
+    aborted: bool,
+    recovering: bool,
 }
 
-impl<'a> Parser<'a> {
+impl<'a> Parser<Chars<'a>> {
     pub fn new(source: &'a str) -> Self {
+        Parser::with_options(source, ParserOptions::default())
+    }
+
+    pub fn with_options(source: &'a str, options: ParserOptions) -> Self {
+        Parser::from_lexer(Lexer::with_options(source, options), options)
+    }
+
+    // Strings without escapes in the resulting tree point into `source`.
+    pub(crate) fn borrowed(source: &'a str, options: ParserOptions) -> Self {
+        let lexer = Lexer::with_options(source, options).borrow_strings();
+        Parser::from_lexer(lexer, options)
+    }
+
+    // `source` must be the input this parser was created with by
+    // `Parser::borrowed`.
+    pub(crate) fn parse_borrowed(
+        mut self,
+        source: &'a str,
+    ) -> (borrowed::Value<'a>, Vec<JSONError>) {
+        let value = self.parse_tree(source);
+        for e in &mut self.errors {
+            if let JSONError::UnexpectedToken(token, _, _) = e {
+                if token.prefix_end.is_some() {
+                    token.value = Some(string_value(token, source).into_owned());
+                }
+            }
+        }
+        (value, self.errors)
+    }
+}
+
+impl<'a> Parser<ByteSource<'a>> {
+    pub fn from_slice(source: &'a [u8], options: ParserOptions) -> Self {
+        Parser::from_lexer(Lexer::from_slice(source, options), options)
+    }
+}
+
+impl<R: Read> Parser<ReadSource<R>> {
+    pub fn from_reader(reader: R, options: ParserOptions) -> Self {
+        Parser::from_lexer(Lexer::from_reader(reader, options), options)
+    }
+}
+
+impl<T: Source> Parser<T> {
+    pub fn from_lexer(lexer: Lexer<T>, options: ParserOptions) -> Self {
         let mut p = Parser {
-            lexer: Lexer::<Chars>::new(source),
+            lexer,
             curr_tok: Token::default(),
             peek_tok: Token::default(),
+            prev_end: Location::new(1, 1),
             errors: vec![],
+            options,
+            depth: 0,
+            aborted: false,
+            recovering: false,
         };
         p.next_token();
         p.next_token();
         p
     }
 
+    /// Same as `ParserOptions::lossless_numbers`.
+    pub fn lossless_numbers(mut self, enable: bool) -> Self {
+        self.options = self.options.lossless_numbers(enable);
+        self
+    }
+
+    /// Same as `ParserOptions::max_depth`.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.options = self.options.max_depth(depth);
+        self
+    }
+
     pub fn next_token(&mut self) {
-        self.curr_tok = self.peek_tok.clone();
-        self.peek_tok = self.lexer.next_token();
+        self.prev_end = self.curr_tok.end;
+        self.curr_tok = std::mem::replace(&mut self.peek_tok, self.lexer.next_token());
+        while self.peek_tok.token_type == TokenType::Comment {
+            self.peek_tok = self.lexer.next_token();
+        }
+        // A failed read looks like the end of the input to the lexer.
+        if let Some(e) = self.lexer.take_io_error() {
+            self.emit_error(JSONError::IOError(e));
+            self.aborted = true;
+        }
+    }
+
+    /// Reads documents one after the other instead of a single one.
+    pub fn documents(self) -> Documents<T> {
+        Documents {
+            parser: self,
+            done: false,
+        }
+    }
+
+    pub fn errors(&self) -> &[JSONError] {
+        &self.errors
+    }
+
+    /// Like `parse_all`, recording the span of every value and key.
+    pub fn parse_all_spanned(mut self) -> (Spanned<spanned::Value>, Vec<JSONError>) {
+        let value = self.parse_tree("");
+        (value, self.errors)
+    }
+
+    /// Parses the whole input and returns the value together with every
+    /// diagnostic collected on the way. The value is best-effort when the
+    /// list is not empty.
+    pub fn parse_all(mut self) -> (Value, Vec<JSONError>) {
+        let value = self.parse();
+        (value, self.errors)
     }
 
     pub fn parse(&mut self) -> Value {
-        let value = self.parse_value();
+        // Tokens from a lexer that does not borrow strings never refer back
+        // to the input.
+        self.parse_tree("")
+    }
+
+    fn parse_tree<'a, V: Tree<'a>>(&mut self, input: &'a str) -> V {
+        let value = self.parse_value(input);
 
-        if self.peek_tok.token_type != TokenType::EOF {
-            self.errors.push(JSONError::UnexpectedToken(
-                self.peek_tok.clone(),
-                self.peek_tok.location,
-            ));
+        if self.curr_tok.token_type != TokenType::EOF {
+            self.unexpected_token(Expected::EndOfInput);
         }
         value
     }
 
-    fn parse_value(&mut self) -> Value {
-        match self.curr_tok.token_type {
-            TokenType::String => self.parse_string(),
+    // On a syntax error the value is replaced by null. Tokens that end the
+    // value in the enclosing array or object are left for it to handle.
+    fn parse_value<'a, V: Tree<'a>>(&mut self, input: &'a str) -> V {
+        let start = self.curr_tok.location;
+        let value = match self.curr_tok.token_type {
+            TokenType::String => V::string(self.parse_string(input)),
             TokenType::Number => self.parse_number(),
             TokenType::True => self.parse_boolean(true),
             TokenType::False => self.parse_boolean(false),
             TokenType::Null => self.parse_null(),
-            TokenType::LeftBrace => self.parse_object(),
-            TokenType::LeftBracket => self.parse_array(),
+            TokenType::LeftBrace | TokenType::LeftBracket => {
+                if self.depth >= self.options.max_depth {
+                    return self.exceed_depth();
+                }
+                self.depth += 1;
+                let value = if self.curr_tok.token_type == TokenType::LeftBrace {
+                    self.parse_object(input)
+                } else {
+                    self.parse_array(input)
+                };
+                self.depth -= 1;
+                value
+            }
             _ => {
-                self.errors.push(JSONError::UnexpectedToken(
-                    self.curr_tok.clone(),
-                    self.curr_tok.location,
-                ));
-                Value::Null
+                self.unexpected_token(Expected::Value);
+                if !self.at_sync_point() {
+                    self.next_token();
+                }
+                V::null()
             }
-        }
+        };
+        value.with_span(Span {
+            start,
+            end: self.prev_end,
+        })
     }
-    fn parse_string(&mut self) -> Value {
-        let value = self.curr_tok.value.clone();
-        if value.is_none() {
-            self.errors.push(JSONError::StringError("".to_string()));
-            return Value::Error(format!("-{}- {:?}", self.curr_tok, self.curr_tok.location));
-        }
-        self.next_token();
-        Value::String(value.unwrap())
+    fn parse_string<'a>(&mut self, input: &'a str) -> Cow<'a, str> {
+        let value = string_value(&mut self.curr_tok, input);
+        self.accept();
+        value
     }
-    fn parse_number(&mut self) -> Value {
-        let value = self.curr_tok.value.clone().unwrap();
-        self.next_token();
-        Value::Number(value.parse::<usize>().unwrap())
+    fn parse_number<'a, V: Tree<'a>>(&mut self) -> V {
+        let value = self.curr_tok.value.take().unwrap_or_default();
+        let number = number::from_token(&value, &self.options);
+        match number {
+            Ok(n) => {
+                self.accept();
+                V::number(n)
+            }
+            Err(e) => {
+                self.emit_error(JSONError::LexcalError(
+                    ErrorCode::InvalidNumber,
+                    e.to_string(),
+                    self.curr_tok.span(),
+                ));
+                self.accept();
+                V::null()
+            }
+        }
     }
-    fn parse_boolean(&mut self, value: bool) -> Value {
-        self.next_token();
-        Value::Boolean(value)
+    fn parse_boolean<'a, V: Tree<'a>>(&mut self, value: bool) -> V {
+        self.accept();
+        V::boolean(value)
     }
-    fn parse_null(&mut self) -> Value {
-        self.next_token();
-        Value::Null
+    fn parse_null<'a, V: Tree<'a>>(&mut self) -> V {
+        self.accept();
+        V::null()
     }
-    fn parse_object(&mut self) -> Value {
-        self.next_token();
-        let mut object = Vec::new();
+    fn parse_object<'a, V: Tree<'a>>(&mut self, input: &'a str) -> V {
+        self.accept();
+        let mut object = V::Members::default();
         while self.curr_tok.token_type != TokenType::RightBrace {
-            let key = self.parse_string().to_string();
-            if !self.expect_token(TokenType::Colon) {
-                self.emit_error(JSONError::LexcalError(
-                    "Expected ':' after object key".to_string(),
-                    self.curr_tok.location,
-                ));
-                break;
-            }
-            let value = self.parse_value();
-            object.push((key, Box::new(value)));
-            self.next_token();
-            if self.expect_token(TokenType::Comma) {
-                self.next_token();
+            if !is_key(&self.curr_tok, &self.options) {
+                self.unexpected_token(Expected::ObjectKey);
+                self.synchronize();
+            } else {
+                let key_span = self.curr_tok.span();
+                let key = self.parse_string(input);
+                // A missing colon is reported, then parsing goes on as if
+                // it was there.
+                if self.curr_tok.token_type == TokenType::Colon {
+                    self.accept();
+                } else {
+                    self.unexpected_token(Expected::Colon);
+                }
+                let value = self.parse_value(input);
+                V::push_member(&mut object, key, key_span, value);
             }
 
-            if self.curr_tok.token_type == TokenType::EOF {
-                self.errors.push(JSONError::UnexpectedToken(
-                    self.curr_tok.clone(),
-                    self.curr_tok.location,
-                ));
-                break;
+            if !self.after_member(TokenType::RightBrace, Expected::CommaOrRightBrace) {
+                return V::object(object);
             }
         }
-        self.next_token();
-        Value::Object(object)
+        self.accept();
+        V::object(object)
     }
 
-    fn parse_array(&mut self) -> Value {
-        self.next_token();
+    fn parse_array<'a, V: Tree<'a>>(&mut self, input: &'a str) -> V {
+        self.accept();
         let mut array = Vec::new();
         while self.curr_tok.token_type != TokenType::RightBracket {
-            let value = self.parse_value();
+            let value = self.parse_value(input);
             array.push(value);
-            if self.curr_tok.token_type == TokenType::Comma {
-                self.next_token();
+
+            if !self.after_member(TokenType::RightBracket, Expected::CommaOrRightBracket) {
+                return V::array(array);
             }
-            if self.curr_tok.token_type == TokenType::RightBracket {
-                break;
+        }
+        self.accept();
+        V::array(array)
+    }
+
+    // Consumes the comma between members, if there is one. Returns false
+    // when the array or object cannot continue: at the end of the input or
+    // at a closing token that belongs to an enclosing one.
+    fn after_member(&mut self, close: TokenType, expected: Expected) -> bool {
+        loop {
+            match self.curr_tok.token_type {
+                TokenType::Comma => {
+                    let comma = self.curr_tok.location;
+                    self.accept();
+                    if self.curr_tok.token_type == close && !self.options.allow_trailing_commas {
+                        self.emit_error(JSONError::TrailingComma(comma));
+                    }
+                    return true;
+                }
+                _ if self.curr_tok.token_type == close => return true,
+                TokenType::RightBrace
+                | TokenType::RightBracket
+                | TokenType::EOF
+                | TokenType::RecordSeparator => {
+                    self.unexpected_token(expected);
+                    return false;
+                }
+                // A missing comma is reported, then parsing goes on as if
+                // it was there.
+                _ if self.at_value() => {
+                    self.unexpected_token(expected);
+                    return true;
+                }
+                _ => {
+                    self.unexpected_token(expected);
+                    self.synchronize();
+                }
             }
-            if self.curr_tok.token_type == TokenType::EOF {
-                self.errors.push(JSONError::UnexpectedToken(
-                    self.curr_tok.clone(),
-                    self.curr_tok.location,
-                ));
-                break;
+        }
+    }
+
+    fn at_value(&self) -> bool {
+        matches!(
+            self.curr_tok.token_type,
+            TokenType::String
+                | TokenType::Number
+                | TokenType::True
+                | TokenType::False
+                | TokenType::Null
+                | TokenType::LeftBrace
+                | TokenType::LeftBracket
+        )
+    }
+
+    fn at_sync_point(&self) -> bool {
+        matches!(
+            self.curr_tok.token_type,
+            TokenType::Comma
+                | TokenType::RightBrace
+                | TokenType::RightBracket
+                | TokenType::EOF
+                | TokenType::RecordSeparator
+        )
+    }
+
+    // The end of the document, or of a text in a JSON text sequence.
+    fn at_end(&self) -> bool {
+        matches!(
+            self.curr_tok.token_type,
+            TokenType::EOF | TokenType::RecordSeparator
+        )
+    }
+
+    // Skips to the next `,`, `]` or `}` that is not nested in an array or
+    // object the skipped tokens open, or to the end of the document.
+    fn synchronize(&mut self) {
+        let mut depth = 0;
+        while !(self.at_end() || (depth == 0 && self.at_sync_point())) {
+            match self.curr_tok.token_type {
+                TokenType::LeftBrace | TokenType::LeftBracket => depth += 1,
+                TokenType::RightBrace | TokenType::RightBracket => depth -= 1,
+                _ => {}
             }
+            self.next_token();
+        }
+    }
+
+    fn exceed_depth<'a, V: Tree<'a>>(&mut self) -> V {
+        self.emit_error(JSONError::DepthLimitExceeded(
+            self.options.max_depth,
+            self.curr_tok.location,
+        ));
+        self.aborted = true;
+        // Skip the rest of the document without recursing into it. In a text
+        // sequence the next text is still read.
+        while !self.at_end() {
+            self.next_token();
         }
+        V::null()
+    }
+
+    // Consumes a token the grammar allows here, which ends recovery from
+    // the last syntax error.
+    fn accept(&mut self) {
+        self.recovering = false;
         self.next_token();
-        Value::Array(array)
     }
 
     fn emit_error(&mut self, e: JSONError) {
-        self.errors.push(e);
+        // Nothing after the depth limit or a read error is parsed, so later
+        // errors would only be noise from the enclosing arrays and objects.
+        if !self.aborted {
+            self.errors.push(e);
+        }
     }
-    fn expect_token(&mut self, token_type: TokenType) -> bool {
-        if self.curr_tok.token_type == token_type {
-            self.next_token();
-            true
-        } else {
-            self.emit_error(JSONError::UnexpectedToken(
-                self.curr_tok.clone(),
-                self.curr_tok.location,
-            ));
-            false
+    // Errors until the next accepted token are likely caused by this one,
+    // so they are not reported.
+    fn unexpected_token(&mut self, expected: Expected) {
+        if self.recovering {
+            return;
+        }
+        self.recovering = true;
+        let e = JSONError::unexpected(self.curr_tok.clone(), expected);
+        self.emit_error(e);
+    }
+}
+
+/// The documents of concatenated JSON, such as `{"a": 1} {"a": 2}`, or of
+/// an RFC 7464 JSON text sequence with `ParserOptions::json_seq`, each with
+/// its span in the input.
+///
+/// Without record separators nothing tells where the next document starts
+/// after a syntax error, so the first error ends the iteration. In a text
+/// sequence the documents after an invalid one are still read.
+#[derive(Debug)]
+pub struct Documents<T: Source> {
+    parser: Parser<T>,
+    done: bool,
+}
+
+impl<T: Source> Iterator for Documents<T> {
+    type Item = Result<Spanned<Value>, JSONError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let p = &mut self.parser;
+        let seq = p.options.json_seq;
+        while seq && p.curr_tok.token_type == TokenType::RecordSeparator {
+            p.accept();
+        }
+        if p.curr_tok.token_type == TokenType::EOF && p.errors.is_empty() {
+            self.done = true;
+            return None;
+        }
+
+        let start = p.curr_tok.location;
+        let value = p.parse_value("");
+        let span = Span {
+            start,
+            end: p.prev_end,
+        };
+        if seq && !p.at_end() {
+            p.unexpected_token(Expected::RecordSeparator);
+            while !p.at_end() {
+                p.next_token();
+            }
+        }
+        p.recovering = false;
+        let errors = std::mem::take(&mut p.errors);
+        // The depth limit only ends the text it was hit in; after a failed
+        // read there is no next one.
+        if p.curr_tok.token_type == TokenType::RecordSeparator {
+            p.aborted = false;
+        }
+        if !errors.is_empty() && (p.aborted || !seq) {
+            self.done = true;
+        }
+        Some(first_error((Spanned { value, span }, errors)))
+    }
+}
+
+// Whether `token` can be an object key in the dialect selected by `options`.
+pub(crate) fn is_key(token: &Token, options: &ParserOptions) -> bool {
+    match token.token_type {
+        TokenType::String => true,
+        // reserved words are valid identifier names too
+        TokenType::Ident | TokenType::True | TokenType::False | TokenType::Null => {
+            options.allow_unquoted_keys
+        }
+        TokenType::Number => {
+            options.allow_unquoted_keys
+                && matches!(token.value.as_deref(), Some("NaN" | "Infinity"))
         }
+        _ => false,
+    }
+}
+
+// A string token's contents, taken out of the token. Only the part the
+// lexer did not leave in the input is owned.
+fn string_value<'a>(token: &mut Token, input: &'a str) -> Cow<'a, str> {
+    let value = token.value.take();
+    // the opening quote is one byte
+    let prefix = |end| &input[token.location.offset + 1..end];
+    match (token.prefix_end.take(), value) {
+        (None, value) => Cow::Owned(value.unwrap_or_default()),
+        (Some(end), None) => Cow::Borrowed(prefix(end)),
+        (Some(end), Some(rest)) => Cow::Owned(prefix(end).to_string() + &rest),
+    }
+}
+
+/// The tree a parse builds, so that owned, borrowed and spanned values
+/// share one implementation of the grammar.
+pub(crate) trait Tree<'a>: Sized {
+    type Members: Default;
+
+    fn string(value: Cow<'a, str>) -> Self;
+    fn number(value: Number) -> Self;
+    fn boolean(value: bool) -> Self;
+    fn null() -> Self;
+    fn array(elements: Vec<Self>) -> Self;
+    fn object(members: Self::Members) -> Self;
+    fn push_member(object: &mut Self::Members, key: Cow<'a, str>, key_span: Span, value: Self);
+
+    // Called on every value once it is complete.
+    fn with_span(self, _span: Span) -> Self {
+        self
     }
 }
 
@@ -213,4 +554,493 @@ mod tests {
         let value = parser.parse();
         println!("{:?}", value);
     }
+
+    #[test]
+    fn test_parse_all() {
+        let src = r#"{"a": [1, 2, {"b": null}], "c": true}"#;
+        let (value, errors) = Parser::new(src).parse_all();
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(
+            Value::Object(vec![
+                (
+                    "a".to_string(),
+                    Box::new(Value::Array(vec![
+                        Value::Number(Number::from(1u64)),
+                        Value::Number(Number::from(2u64)),
+                        Value::Object(vec![("b".to_string(), Box::new(Value::Null))]),
+                    ]))
+                ),
+                ("c".to_string(), Box::new(Value::Boolean(true))),
+            ]),
+            value
+        );
+    }
+
+    #[test]
+    fn test_parse_number() {
+        let inputs = vec![
+            ("0", Number::from(0u64)),
+            ("-1", Number::from(-1i64)),
+            ("2.5", Number::from_f64(2.5).unwrap()),
+            ("-0.5e-3", Number::from_f64(-0.5e-3).unwrap()),
+            ("1.5E+10", Number::from_f64(1.5e10).unwrap()),
+            ("18446744073709551615", Number::from(u64::MAX)),
+        ];
+
+        for (input, expected) in inputs {
+            assert_eq!(Value::Number(expected), crate::from_str(input).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_parse_lossless_number() {
+        let inputs = vec![
+            "123456789012345678901234567890",
+            "-340282366920938463463374607431768211455",
+            "0.100000000000000000000000000001",
+            "1e+400",
+        ];
+
+        for input in inputs {
+            let options = ParserOptions::new().lossless_numbers(true);
+            let (value, errors) = Parser::with_options(input, options).parse_all();
+            assert!(errors.is_empty(), "{:?}", errors);
+            match value {
+                Value::Number(n) => assert_eq!(Some(input), n.as_str()),
+                _ => panic!("must be a number"),
+            }
+        }
+
+        let (value, _) = Parser::new("1e+400").parse_all();
+        assert_eq!(Value::Null, value);
+        let (value, _) = Parser::new("1e+400").lossless_numbers(true).parse_all();
+        assert_eq!(Value::Number(Number::from_raw("1e+400").unwrap()), value);
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let src = "[".repeat(100_000);
+        let (_, errors) = Parser::new(&src).parse_all();
+        assert_eq!(1, errors.len());
+        match &errors[0] {
+            JSONError::DepthLimitExceeded(depth, location) => {
+                assert_eq!(DEFAULT_MAX_DEPTH, *depth);
+                assert_eq!(DEFAULT_MAX_DEPTH + 1, location.column);
+            }
+            e => panic!("unexpected error {:?}", e),
+        }
+
+        let src = r#"[{"a": [1]}]"#;
+        let parse = |src, depth| {
+            let options = ParserOptions::new().max_depth(depth);
+            Parser::with_options(src, options).parse_all().1
+        };
+        assert!(parse(src, 3).is_empty());
+        assert!(matches!(
+            parse(src, 2)[..],
+            [JSONError::DepthLimitExceeded(2, _)]
+        ));
+        assert!(parse("1", 0).is_empty());
+        let (_, errors) = Parser::new(src).max_depth(2).parse_all();
+        assert!(matches!(errors[..], [JSONError::DepthLimitExceeded(2, _)]));
+    }
+
+    #[test]
+    fn test_strict_dialect() {
+        let inputs = vec![
+            "[1, 2,]",
+            r#"{"a": 1,}"#,
+            "[1, // comment\n 2]",
+            "/* comment */ 1",
+            "{a: 1}",
+            "['a']",
+            "NaN",
+            "-Infinity",
+            "key",
+            r#"{"a": value}"#,
+        ];
+
+        for input in inputs {
+            let (_, errors) = Parser::new(input).parse_all();
+            assert!(!errors.is_empty(), "{} should be rejected", input);
+        }
+    }
+
+    #[test]
+    fn test_lenient_dialect() {
+        let inputs = vec![
+            ("[1, 2,]", "[1,2]"),
+            (r#"{"a": 1,}"#, r#"{"a":1}"#),
+            ("[1, // comment\n 2]", "[1,2]"),
+            ("/* multi\nline */ [1 /* 2 */]// end", "[1]"),
+            ("{a: 1, _b2: 2}", r#"{"a":1,"_b2":2}"#),
+            (r#"['a"b', 'c\'d', "e'f"]"#, r#"["a\"b","c'd","e'f"]"#),
+            ("[NaN, Infinity, -Infinity]", "[null,null,null]"),
+        ];
+
+        for (input, expected) in inputs {
+            let (value, errors) = Parser::with_options(input, ParserOptions::lenient()).parse_all();
+            assert!(errors.is_empty(), "{}: {:?}", input, errors);
+            assert_eq!(expected, value.to_string());
+        }
+
+        let options = ParserOptions::new().allow_nan_infinity(true);
+        match crate::from_str_with_options("-Infinity", options).unwrap() {
+            Value::Number(n) => assert_eq!(Some(f64::NEG_INFINITY), n.as_f64()),
+            _ => panic!("must be a number"),
+        }
+
+        let inputs = vec![
+            "[,]", "{,}", "[1,,]", "{a 1}", "[a]", "[-NaN]", "/", "/* open",
+        ];
+        for input in inputs {
+            let (_, errors) = Parser::with_options(input, ParserOptions::lenient()).parse_all();
+            assert!(!errors.is_empty(), "{} should be rejected", input);
+        }
+    }
+
+    #[test]
+    fn test_jsonc() {
+        let src = r#"
+        // tsconfig-style file
+        {
+            /* compiler settings */
+            "compilerOptions": {
+                "strict": true, // always
+                "paths": {
+                    "@/*": ["src/*"], // trailing comma below
+                },
+            },
+        }
+        "#;
+
+        let expected = r#"{"compilerOptions":{"strict":true,"paths":{"@/*":["src/*"]}}}"#;
+        for options in [
+            ParserOptions::jsonc(),
+            ParserOptions::jsonc().emit_comments(true),
+        ] {
+            let (value, errors) = Parser::with_options(src, options).parse_all();
+            assert!(errors.is_empty(), "{:?}", errors);
+            assert_eq!(expected, value.to_string());
+        }
+
+        let (_, errors) = Parser::with_options("[1 /* open", ParserOptions::jsonc()).parse_all();
+        assert!(matches!(
+            errors[..],
+            [JSONError::LexcalError(ErrorCode::UnterminatedComment, _, _)]
+        ));
+    }
+
+    #[test]
+    fn test_json5() {
+        // the example from https://json5.org
+        let src = r#"{
+          // comments
+          unquoted: 'and you can quote me on that',
+          singleQuotes: 'I can use "double quotes" here',
+          lineBreaks: "Look, Mom! \
+No \\n's!",
+          hexadecimal: 0xdecaf,
+          leadingDecimalPoint: .8675309, andTrailing: 8675309.,
+          positiveSign: +1,
+          trailingComma: 'in objects', andIn: ['arrays',],
+          "backwardsCompatible": "with JSON",
+        }"#;
+
+        let expected = r#"{"unquoted":"and you can quote me on that","singleQuotes":"I can use \"double quotes\" here","lineBreaks":"Look, Mom! No \\n's!","hexadecimal":912559,"leadingDecimalPoint":0.8675309,"andTrailing":8675309.0,"positiveSign":1,"trailingComma":"in objects","andIn":["arrays"],"backwardsCompatible":"with JSON"}"#;
+        let (value, errors) = Parser::with_options(src, ParserOptions::json5()).parse_all();
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(expected, value.to_string());
+
+        let inputs = vec![
+            (
+                "{$key_1: 1, café: 2, \\u0061b: 3}",
+                r#"{"$key_1":1,"café":2,"ab":3}"#,
+            ),
+            (
+                "{null: 1, true: 2, Infinity: 3}",
+                r#"{"null":1,"true":2,"Infinity":3}"#,
+            ),
+            ("['\\x41\\v\\0\\q\\\r\nb']", r#"["A\u000b\u0000qb"]"#),
+            (
+                "\u{feff}\u{a0}[\u{2028}-0x10,\u{3000}+.5e1]\u{b}",
+                "[-16,5.0]",
+            ),
+        ];
+        for (input, expected) in inputs {
+            let (value, errors) = Parser::with_options(input, ParserOptions::json5()).parse_all();
+            assert!(errors.is_empty(), "{}: {:?}", input, errors);
+            assert_eq!(expected, value.to_string());
+        }
+
+        let options = ParserOptions::json5().allow_nan_infinity(true);
+        let (value, _) = Parser::with_options("[+Infinity, -NaN]", options).parse_all();
+        match value {
+            Value::Array(v) => match &v[..] {
+                [Value::Number(inf), Value::Number(nan)] => {
+                    assert_eq!(Some(f64::INFINITY), inf.as_f64());
+                    assert!(nan.as_f64().unwrap().is_nan());
+                }
+                _ => panic!("must be two numbers"),
+            },
+            _ => panic!("must be an array"),
+        }
+
+        let inputs = vec![
+            "0x",
+            "'\\1'",
+            "'\\01'",
+            "'\\xG0'",
+            "{a-b: 1}",
+            "{\\x61: 1}",
+            "+-1",
+        ];
+        for input in inputs {
+            let (_, errors) = Parser::with_options(input, ParserOptions::json5()).parse_all();
+            assert!(!errors.is_empty(), "{} should be rejected", input);
+        }
+        for input in ["+1", ".5", "5.", "0x1", "{$a: 1}", "'\\v'"] {
+            let (_, errors) = Parser::with_options(input, ParserOptions::lenient()).parse_all();
+            assert!(!errors.is_empty(), "{} should be rejected", input);
+        }
+    }
+
+    #[test]
+    fn test_from_slice() {
+        let src = r#"{"名前": "太郎", "tags": ["a\u00e9", "\ud83d\ude00"]}"#;
+        assert_eq!(
+            crate::from_str(src).unwrap(),
+            crate::from_slice(src.as_bytes()).unwrap()
+        );
+
+        let src = b"{\"a\": \"\xe6\x97\xa5\xe6\x9c\"}";
+        let (_, errors) = Parser::from_slice(src, ParserOptions::default()).parse_all();
+        match &errors[0] {
+            JSONError::LexcalError(ErrorCode::InvalidUtf8, message, _) => {
+                assert_eq!("invalid UTF-8 at byte offset 10", message)
+            }
+            e => panic!("unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let src = r#"{"key": ["value", 1.5, {"日本": true}]}"#;
+        assert_eq!(
+            crate::from_str(src).unwrap(),
+            crate::from_reader(src.as_bytes()).unwrap()
+        );
+
+        // a large document goes through several buffer refills
+        let src = format!("[{}1]", r#""\u00e9日本語","#.repeat(10_000));
+        assert_eq!(
+            crate::from_str(&src).unwrap(),
+            crate::from_reader(std::io::Cursor::new(src.as_bytes())).unwrap()
+        );
+
+        assert!(matches!(
+            crate::from_reader(&b"[1, \xff]"[..]),
+            Err(JSONError::LexcalError(ErrorCode::InvalidUtf8, _, _))
+        ));
+    }
+
+    #[test]
+    fn test_from_reader_io_error() {
+        struct Failing<'a>(&'a [u8]);
+        impl Read for Failing<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                if self.0.is_empty() {
+                    return Err(std::io::Error::other("connection reset"));
+                }
+                let n = self.0.len().min(buf.len());
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+
+        for src in [&br#"{"a": [1, 2"#[..], br#"{"a": "unterminated"#, b"1"] {
+            let (_, errors) =
+                Parser::from_reader(Failing(src), ParserOptions::default()).parse_all();
+            assert!(
+                matches!(errors[..], [JSONError::IOError(_)]),
+                "{:?}",
+                errors
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_all_collects_errors() {
+        let inputs = vec![
+            r#"{"a" 1}"#,
+            r#"{"a": 1 "b": 2}"#,
+            r#"[1 2]"#,
+            r#"[1, 2"#,
+            r#""abc"#,
+            r#"1 2"#,
+            r#"@"#,
+            r#""#,
+        ];
+
+        for input in inputs {
+            let (_, errors) = Parser::new(input).parse_all();
+            assert!(!errors.is_empty(), "{} should be rejected", input);
+        }
+    }
+
+    #[test]
+    fn test_recovery() {
+        let src = r#"{
+  "name": "toy"
+  "port" 80,
+  "tags": [1, 2,],
+  "on": tru,
+  "x": [1 : 2],
+  "ok": true
+}"#;
+        let (value, errors) = Parser::new(src).parse_all();
+        let found: Vec<_> = errors
+            .iter()
+            .map(|e| {
+                let location = e.location().unwrap();
+                (e.code(), location.line, location.column)
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (ErrorCode::UnexpectedToken, 3, 3),
+                (ErrorCode::UnexpectedToken, 3, 10),
+                (ErrorCode::TrailingComma, 4, 16),
+                (ErrorCode::UnexpectedToken, 5, 9),
+                (ErrorCode::UnexpectedToken, 6, 11),
+            ],
+            found,
+            "{:?}",
+            errors
+        );
+        assert_eq!(
+            r#"{"name":"toy","port":80,"tags":[1,2],"on":null,"x":[1],"ok":true}"#,
+            value.to_string()
+        );
+
+        // one mistake, one error
+        let inputs = vec![
+            (r#"[[1}, 2]"#, "[[1]]"),
+            (r#"{"a": 1]"#, r#"{"a":1}"#),
+            (r#"[1, 2"#, "[1,2]"),
+            (r#"[1,, 3]"#, "[1,null,3]"),
+            (r#"{"a": }"#, r#"{"a":null}"#),
+            (r#"{1: 2, "b": [3]}"#, r#"{"b":[3]}"#),
+            (r#"[{"a": {"b" [1, 2]}}, 4]"#, r#"[{"a":{"b":[1,2]}},4]"#),
+            (r#"[1, @, {"a": [3]}, 2]"#, r#"[1,null,{"a":[3]},2]"#),
+            (r#"[1] 2"#, "[1]"),
+        ];
+        for (input, expected) in inputs {
+            let (value, errors) = Parser::new(input).parse_all();
+            assert_eq!(1, errors.len(), "{}: {:?}", input, errors);
+            assert_eq!(expected, value.to_string(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_documents() {
+        let src = "{\"a\": 1} [2]\n\"three\"4 null{}";
+        let docs: Vec<_> = Parser::new(src).documents().map(Result::unwrap).collect();
+        let found: Vec<_> = docs
+            .iter()
+            .map(|d| (d.value.to_string(), &src[d.span.range()]))
+            .collect();
+        assert_eq!(
+            vec![
+                (r#"{"a":1}"#.to_string(), "{\"a\": 1}"),
+                ("[2]".to_string(), "[2]"),
+                (r#""three""#.to_string(), "\"three\""),
+                ("4".to_string(), "4"),
+                ("null".to_string(), "null"),
+                ("{}".to_string(), "{}"),
+            ],
+            found
+        );
+        assert_eq!((2, 1), (docs[2].span.start.line, docs[2].span.start.column));
+        assert_eq!(0, Parser::new(" \n").documents().count());
+
+        // the first error is the last item
+        let docs: Vec<_> = Parser::new("1 [2,] 3").documents().collect();
+        assert!(matches!(
+            docs[..],
+            [Ok(_), Err(JSONError::TrailingComma(_))]
+        ));
+        let docs: Vec<_> = Parser::new("1 ] 2").documents().collect();
+        assert_eq!(2, docs.len());
+        assert_eq!(
+            ErrorCode::UnexpectedToken,
+            docs[1].as_ref().unwrap_err().code()
+        );
+    }
+
+    #[test]
+    fn test_json_seq() {
+        let options = ParserOptions::new().json_seq(true);
+        let src = "\u{1e}{\"a\": 1}\n\u{1e}[1, 2\n\u{1e}\u{1e}\"x\"\n\u{1e}1 2\n\u{1e}{\"b\": [}\n\u{1e}true\n";
+        let docs: Vec<_> = Parser::with_options(src, options)
+            .documents()
+            .map(|d| match d {
+                Ok(d) => Ok((d.value.to_string(), d.span.start.line)),
+                Err(e) => Err((e.code(), e.location().unwrap().line)),
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                Ok((r#"{"a":1}"#.to_string(), 1)),
+                Err((ErrorCode::UnexpectedToken, 3)),
+                Ok((r#""x""#.to_string(), 3)),
+                Err((ErrorCode::UnexpectedToken, 4)),
+                Err((ErrorCode::UnexpectedToken, 5)),
+                Ok(("true".to_string(), 6)),
+            ],
+            docs
+        );
+
+        let e = Parser::with_options("\u{1e}[1\u{1e}", options)
+            .documents()
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(
+            "expected ',' or ']' after array element, found record separator at line 1, column 4",
+            e.to_string()
+        );
+        // the depth limit only ends the text it is hit in
+        let src = "\u{1e}[[[1]]]\n\u{1e}1\n\u{1e}2\n";
+        let docs: Vec<_> = Parser::from_reader(src.as_bytes(), options.max_depth(2))
+            .documents()
+            .map(|d| d.map(|d| d.value.to_string()).map_err(|e| e.code()))
+            .collect();
+        assert_eq!(
+            vec![
+                Err(ErrorCode::DepthLimitExceeded),
+                Ok("1".to_string()),
+                Ok("2".to_string()),
+            ],
+            docs
+        );
+
+        // a separator is only accepted between documents
+        assert!(crate::from_str_with_options("\u{1e}1", options).is_err());
+        assert!(crate::from_str("\u{1e}1").is_err());
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(Value::Boolean(true), crate::from_str(" true ").unwrap());
+        assert!(matches!(
+            crate::from_str(r#"{"a": }"#),
+            Err(JSONError::UnexpectedToken(_, _, _))
+        ));
+        assert!(matches!(
+            crate::from_str(r#"["a", "b"#),
+            Err(JSONError::LexcalError(ErrorCode::UnterminatedString, _, _))
+        ));
+    }
 }
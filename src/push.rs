@@ -0,0 +1,317 @@
+//! Parsing input that arrives in chunks, such as a request body, while it
+//! is still being received.
+
+use crate::{
+    error::JSONError,
+    event::{Event, Grammar},
+    lexer::{Checkpoint, Lexer},
+    options::ParserOptions,
+    source::ChunkSource,
+    token::TokenType,
+    value::Value,
+};
+
+/// A parser the input is pushed into, instead of one that pulls it from a
+/// source.
+///
+/// The document is built as the input comes in, and chunks may split it
+/// anywhere, even inside a UTF-8 sequence. A token cut short by the end of
+/// a chunk is carried on where the lexer left it, so every byte is lexed
+/// once. Only the bytes of an unfinished UTF-8 sequence or escape are kept
+/// between calls to `feed`.
+///
+/// ```
+/// use toyjson::push::PushParser;
+///
+/// let mut parser = PushParser::new();
+/// for chunk in [&b"{\"na"[..], b"me\": \"caf\xc3", b"\xa9\", \"n\": 1", b"2}"] {
+///     parser.feed(chunk).unwrap();
+/// }
+/// let value = parser.finish().unwrap();
+/// assert_eq!(r#"{"name":"café","n":12}"#, value.to_string());
+/// ```
+#[derive(Debug)]
+pub struct PushParser {
+    options: ParserOptions,
+    // the input the lexer has not consumed yet
+    pending: Vec<u8>,
+    // where the lexer stopped, and the token it was in the middle of
+    checkpoint: Checkpoint,
+    grammar: Grammar,
+    builder: Builder,
+    failed: bool,
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        PushParser::with_options(ParserOptions::default())
+    }
+}
+
+impl PushParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: ParserOptions) -> Self {
+        PushParser {
+            options,
+            pending: Vec::new(),
+            checkpoint: Checkpoint::default(),
+            grammar: Grammar::new(options),
+            builder: Builder::default(),
+            failed: false,
+        }
+    }
+
+    /// Parses as much of the input as `chunk` completes, and reports any
+    /// error found in it; after one, the parser is done.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), JSONError> {
+        if self.failed {
+            return Err(failed());
+        }
+        self.pending.extend_from_slice(chunk);
+        self.run(false)
+    }
+
+    /// Ends the input and returns the document.
+    pub fn finish(mut self) -> Result<Value, JSONError> {
+        if self.failed {
+            return Err(failed());
+        }
+        self.run(true)?;
+        Ok(self.builder.root.take().unwrap_or(Value::Null))
+    }
+
+    fn run(&mut self, last: bool) -> Result<(), JSONError> {
+        let result = self.lex(last);
+        if result.is_err() {
+            self.failed = true;
+        }
+        result
+    }
+
+    // Lexes `pending` up to its end and drops the bytes consumed.
+    fn lex(&mut self, last: bool) -> Result<(), JSONError> {
+        let offset = self.checkpoint.offset();
+        let source = ChunkSource::new(&self.pending, offset, last);
+        let checkpoint = std::mem::take(&mut self.checkpoint);
+        let mut lexer = Lexer::resume(source, self.options, checkpoint);
+        let result = loop {
+            if self.grammar.is_done() {
+                break Ok(());
+            }
+            let token = lexer.next_token();
+            if let Some(checkpoint) = lexer.take_checkpoint() {
+                let consumed = checkpoint.offset() - offset;
+                self.checkpoint = checkpoint;
+                self.pending.drain(..consumed);
+                return Ok(());
+            }
+            if token.token_type == TokenType::Comment {
+                continue;
+            }
+            match self.grammar.accept(token) {
+                Ok(Some(event)) => self.builder.push(event.value),
+                Ok(None) => {}
+                Err(e) => break Err(e),
+            }
+        };
+        // The parse is over, with nothing left to carry on.
+        self.pending.clear();
+        result
+    }
+}
+
+fn failed() -> JSONError {
+    JSONError::Misuse("parsing already stopped at an error".to_string())
+}
+
+// Assembles a `Value` from the events of a document.
+#[derive(Debug, Default)]
+struct Builder {
+    // the arrays and objects being filled, with the key of the next member
+    stack: Vec<(Value, Option<String>)>,
+    root: Option<Value>,
+}
+
+impl Builder {
+    fn push(&mut self, event: Event) {
+        let value = match event {
+            Event::StartObject => {
+                self.stack.push((Value::Object(vec![]), None));
+                return;
+            }
+            Event::StartArray => {
+                self.stack.push((Value::Array(vec![]), None));
+                return;
+            }
+            Event::Key(key) => {
+                if let Some((_, next_key)) = self.stack.last_mut() {
+                    *next_key = Some(key);
+                }
+                return;
+            }
+            Event::EndObject | Event::EndArray => match self.stack.pop() {
+                Some((value, _)) => value,
+                None => return,
+            },
+            Event::String(s) => Value::String(s),
+            Event::Number(n) => Value::Number(n),
+            Event::Boolean(b) => Value::Boolean(b),
+            Event::Null => Value::Null,
+        };
+        match self.stack.last_mut() {
+            Some((Value::Array(elements), _)) => elements.push(value),
+            Some((Value::Object(members), key)) => {
+                members.push((key.take().unwrap_or_default(), Box::new(value)))
+            }
+            _ => self.root = Some(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_chunked(
+        input: &[u8],
+        size: usize,
+        options: ParserOptions,
+    ) -> Result<Value, JSONError> {
+        let mut parser = PushParser::with_options(options);
+        for chunk in input.chunks(size) {
+            parser.feed(chunk)?;
+        }
+        parser.finish()
+    }
+
+    #[test]
+    fn test_push_parser() {
+        let src = r#" {"name": "caf\u00e9 \ud83d\ude00 日本", "n": [0, -12.5e-3, 1234567],
+            "ok": true, "no": false, "none": null, "esc": "a\"b\\c\n", "e": {}, "": []}
+        "#;
+        let expected = crate::from_str(src).unwrap();
+        for size in 1..=src.len() {
+            let value = parse_chunked(src.as_bytes(), size, ParserOptions::default()).unwrap();
+            assert_eq!(expected, value, "chunks of {}", size);
+        }
+        // one split at every position
+        for i in 0..=src.len() {
+            let mut parser = PushParser::new();
+            parser.feed(&src.as_bytes()[..i]).unwrap();
+            parser.feed(&src.as_bytes()[i..]).unwrap();
+            assert_eq!(expected, parser.finish().unwrap(), "split at {}", i);
+        }
+
+        let src = "// settings\n{\"a\": [1, 2,], /* b */ \"b\": 'x',}";
+        let expected = crate::from_str_with_options(src, ParserOptions::json5()).unwrap();
+        for size in 1..=4 {
+            let value = parse_chunked(src.as_bytes(), size, ParserOptions::json5()).unwrap();
+            assert_eq!(expected, value);
+        }
+    }
+
+    #[test]
+    fn test_push_parser_errors() {
+        let inputs = vec![
+            "",
+            "[1, 2,]",
+            "{\"a\" 1}",
+            "[1e999]",
+            "[\"\\q\"]",
+            "[1",
+            "\"abc",
+            "[1] 2",
+            "{\"a\":\n  [tru]}",
+        ];
+        for input in inputs {
+            let expected = crate::from_str(input).unwrap_err();
+            for size in [1, 2, 5] {
+                let e =
+                    parse_chunked(input.as_bytes(), size, ParserOptions::default()).unwrap_err();
+                assert_eq!(format!("{:?}", expected), format!("{:?}", e), "{}", input);
+            }
+        }
+
+        let input = b"[\"a\", \"\xe6\x97\xa5\xe6\x9c\"]";
+        let expected = crate::from_slice(input).unwrap_err();
+        let e = parse_chunked(input, 3, ParserOptions::default()).unwrap_err();
+        assert_eq!(format!("{:?}", expected), format!("{:?}", e));
+
+        // reported before the end of the input
+        let mut parser = PushParser::new();
+        parser.feed(b"[1, 2").unwrap();
+        assert!(parser.feed(b" 3 ").is_err());
+        let e = parser.feed(b"]").unwrap_err();
+        assert_eq!(crate::error::ErrorCode::Misuse, e.code());
+
+        // and right after a long cut-off token
+        let mut parser = PushParser::new();
+        parser.feed(b"[\"").unwrap();
+        parser.feed(&[b'x'; 100]).unwrap();
+        assert!(parser.feed(b"\" }").is_err());
+    }
+
+    #[test]
+    fn test_push_parser_split_tokens() {
+        let src = "/* a\r\n*/ {\"\\u00e9\\ud83d\\ude00\\n\": [0x1F, -.5, 12.5e-3, +1E+2, \r\n -Infinity, \
+                   '\\x41\\\r\nb'], \\u0061b: null // c\r\n}";
+        let options = ParserOptions::json5().allow_nan_infinity(true);
+        let expected = crate::from_str_with_options(src, options).unwrap();
+        for i in 0..=src.len() {
+            for j in i..=src.len() {
+                let mut parser = PushParser::with_options(options);
+                parser.feed(&src.as_bytes()[..i]).unwrap();
+                parser.feed(&src.as_bytes()[i..j]).unwrap();
+                parser.feed(&src.as_bytes()[j..]).unwrap();
+                assert_eq!(
+                    expected,
+                    parser.finish().unwrap(),
+                    "split at {} and {}",
+                    i,
+                    j
+                );
+            }
+        }
+
+        // errors keep their line and column across \r\n split in two
+        let src = "[1,\r\n\r\n  tru]";
+        let expected = crate::from_str(src).unwrap_err();
+        for i in 0..=src.len() {
+            let mut parser = PushParser::new();
+            let e = parser
+                .feed(&src.as_bytes()[..i])
+                .and_then(|_| parser.feed(&src.as_bytes()[i..]))
+                .and_then(|_| parser.finish())
+                .unwrap_err();
+            assert_eq!(
+                format!("{:?}", expected),
+                format!("{:?}", e),
+                "split at {}",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn test_push_parser_long_token() {
+        let text = "x".repeat(1 << 20);
+        let src = format!("[\"{}\", 1]", text);
+        let mut parser = PushParser::new();
+        for chunk in src.as_bytes().chunks(1) {
+            parser.feed(chunk).unwrap();
+            // at most an unfinished escape
+            assert!(parser.pending.len() <= 12);
+        }
+        let value = parser.finish().unwrap();
+        assert_eq!(
+            Value::Array(vec![
+                Value::String(text),
+                Value::Number(crate::number::Number::from(1u64))
+            ]),
+            value
+        );
+    }
+}
@@ -0,0 +1,467 @@
+use std::io::{self, Write};
+
+use crate::{error::JSONError, value::Value};
+
+pub fn to_writer<W: Write>(mut writer: W, value: &Value) -> Result<(), JSONError> {
+    write_value(&mut writer, value)?;
+    Ok(())
+}
+
+/// Fails only for a `Value::Error`, which has no JSON form.
+pub fn to_string(value: &Value) -> Result<String, JSONError> {
+    let mut buf = Vec::new();
+    write_value(&mut buf, value)?;
+    // Only valid UTF-8 is ever written.
+    Ok(String::from_utf8(buf).unwrap())
+}
+
+pub(crate) fn write_value<W: Write>(w: &mut W, value: &Value) -> Result<(), JSONError> {
+    write(w, value, false)
+}
+
+// Compact JSON, except that `Value::Error` placeholders are written as
+// their message, for `Display`.
+pub(crate) fn to_display_string(value: &Value) -> String {
+    let mut buf = Vec::new();
+    // Writing into a Vec cannot fail, and placeholders are allowed.
+    write(&mut buf, value, true).unwrap();
+    String::from_utf8(buf).unwrap()
+}
+
+fn write<W: Write>(w: &mut W, value: &Value, placeholders: bool) -> Result<(), JSONError> {
+    match value {
+        Value::String(s) => write_string(w, s)?,
+        // Like JSON.stringify, NaN and the infinities become null.
+        Value::Number(n) if !n.is_finite() => w.write_all(b"null")?,
+        Value::Number(n) => write!(w, "{}", n)?,
+        Value::Boolean(b) => write!(w, "{}", b)?,
+        Value::Object(o) => {
+            w.write_all(b"{")?;
+            for (i, (key, value)) in o.iter().enumerate() {
+                if i > 0 {
+                    w.write_all(b",")?;
+                }
+                write_string(w, key)?;
+                w.write_all(b":")?;
+                write(w, value, placeholders)?;
+            }
+            w.write_all(b"}")?
+        }
+        Value::Array(v) => {
+            w.write_all(b"[")?;
+            for (i, value) in v.iter().enumerate() {
+                if i > 0 {
+                    w.write_all(b",")?;
+                }
+                write(w, value, placeholders)?;
+            }
+            w.write_all(b"]")?
+        }
+        Value::Null => w.write_all(b"null")?,
+        Value::Error(message) if placeholders => w.write_all(message.as_bytes())?,
+        // Placeholders for values that failed to parse have no JSON form.
+        Value::Error(message) => return Err(JSONError::Unserializable(message.clone())),
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Spaces(usize),
+    Tabs(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Newline {
+    Lf,
+    CrLf,
+}
+
+impl Newline {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Newline::Lf => "\n",
+            Newline::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Writes a `Value` over multiple lines.
+///
+/// Non-empty arrays and objects are broken into one member per line unless
+/// their single-line form fits in `max_inline_width` characters.
+#[derive(Debug, Clone)]
+pub struct PrettyFormatter {
+    indent: Indent,
+    space_before_colon: bool,
+    space_after_colon: bool,
+    space_after_comma: bool,
+    newline: Newline,
+    max_inline_width: usize,
+    trailing_newline: bool,
+}
+
+impl Default for PrettyFormatter {
+    fn default() -> Self {
+        PrettyFormatter {
+            indent: Indent::Spaces(2),
+            space_before_colon: false,
+            space_after_colon: true,
+            space_after_comma: true,
+            newline: Newline::Lf,
+            max_inline_width: 0,
+            trailing_newline: false,
+        }
+    }
+}
+
+impl PrettyFormatter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn indent(mut self, indent: Indent) -> Self {
+        self.indent = indent;
+        self
+    }
+    pub fn space_before_colon(mut self, enable: bool) -> Self {
+        self.space_before_colon = enable;
+        self
+    }
+    pub fn space_after_colon(mut self, enable: bool) -> Self {
+        self.space_after_colon = enable;
+        self
+    }
+    /// Only affects arrays and objects written on a single line.
+    pub fn space_after_comma(mut self, enable: bool) -> Self {
+        self.space_after_comma = enable;
+        self
+    }
+    pub fn newline(mut self, newline: Newline) -> Self {
+        self.newline = newline;
+        self
+    }
+    /// `0` disables inlining; empty arrays and objects are always inline.
+    pub fn max_inline_width(mut self, width: usize) -> Self {
+        self.max_inline_width = width;
+        self
+    }
+    pub fn trailing_newline(mut self, enable: bool) -> Self {
+        self.trailing_newline = enable;
+        self
+    }
+
+    pub fn to_writer<W: Write>(&self, mut writer: W, value: &Value) -> Result<(), JSONError> {
+        self.write_value(&mut writer, value, 0)?;
+        if self.trailing_newline {
+            writer.write_all(self.newline.as_str().as_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn to_string(&self, value: &Value) -> Result<String, JSONError> {
+        let mut buf = Vec::new();
+        self.to_writer(&mut buf, value)?;
+        Ok(String::from_utf8(buf).unwrap())
+    }
+
+    fn write_value<W: Write>(
+        &self,
+        w: &mut W,
+        value: &Value,
+        depth: usize,
+    ) -> Result<(), JSONError> {
+        let is_empty = match value {
+            Value::Object(o) => o.is_empty(),
+            Value::Array(v) => v.is_empty(),
+            _ => true,
+        };
+        if is_empty {
+            return write_value(w, value);
+        }
+        if let Some(line) = self.inline(value) {
+            w.write_all(line.as_bytes())?;
+            return Ok(());
+        }
+
+        match value {
+            Value::Object(o) => {
+                w.write_all(b"{")?;
+                for (i, (key, value)) in o.iter().enumerate() {
+                    if i > 0 {
+                        w.write_all(b",")?;
+                    }
+                    self.write_newline(w, depth + 1)?;
+                    write_string(w, key)?;
+                    self.write_colon(w)?;
+                    self.write_value(w, value, depth + 1)?;
+                }
+                self.write_newline(w, depth)?;
+                w.write_all(b"}")?
+            }
+            Value::Array(v) => {
+                w.write_all(b"[")?;
+                for (i, value) in v.iter().enumerate() {
+                    if i > 0 {
+                        w.write_all(b",")?;
+                    }
+                    self.write_newline(w, depth + 1)?;
+                    self.write_value(w, value, depth + 1)?;
+                }
+                self.write_newline(w, depth)?;
+                w.write_all(b"]")?
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    fn write_newline<W: Write>(&self, w: &mut W, depth: usize) -> io::Result<()> {
+        w.write_all(self.newline.as_str().as_bytes())?;
+        let (ch, width) = match self.indent {
+            Indent::Spaces(n) => (b' ', n),
+            Indent::Tabs(n) => (b'\t', n),
+        };
+        w.write_all(&vec![ch; width * depth])
+    }
+
+    fn write_colon<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        if self.space_before_colon {
+            w.write_all(b" ")?;
+        }
+        w.write_all(b":")?;
+        if self.space_after_colon {
+            w.write_all(b" ")?;
+        }
+        Ok(())
+    }
+
+    // The single-line form of `value`, if it fits in `max_inline_width`.
+    fn inline(&self, value: &Value) -> Option<String> {
+        if self.max_inline_width == 0 {
+            return None;
+        }
+        let mut buf = Vec::new();
+        // A char is at most 4 bytes, so anything longer can never fit.
+        self.write_inline(&mut buf, value, self.max_inline_width * 4)
+            .ok()?;
+        let line = String::from_utf8(buf).unwrap();
+        if line.chars().count() <= self.max_inline_width {
+            Some(line)
+        } else {
+            None
+        }
+    }
+
+    fn write_inline(&self, w: &mut Vec<u8>, value: &Value, limit: usize) -> Result<(), JSONError> {
+        match value {
+            Value::Object(o) => {
+                w.write_all(b"{")?;
+                for (i, (key, value)) in o.iter().enumerate() {
+                    if i > 0 {
+                        self.write_comma(w)?;
+                    }
+                    write_string(w, key)?;
+                    self.write_colon(w)?;
+                    self.write_inline(w, value, limit)?;
+                }
+                w.write_all(b"}")?;
+            }
+            Value::Array(v) => {
+                w.write_all(b"[")?;
+                for (i, value) in v.iter().enumerate() {
+                    if i > 0 {
+                        self.write_comma(w)?;
+                    }
+                    self.write_inline(w, value, limit)?;
+                }
+                w.write_all(b"]")?;
+            }
+            _ => write_value(w, value)?,
+        }
+        if w.len() > limit {
+            return Err(io::Error::from(io::ErrorKind::WriteZero).into());
+        }
+        Ok(())
+    }
+
+    fn write_comma<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        if self.space_after_comma {
+            w.write_all(b", ")
+        } else {
+            w.write_all(b",")
+        }
+    }
+}
+
+pub fn to_string_pretty(value: &Value) -> Result<String, JSONError> {
+    PrettyFormatter::default().to_string(value)
+}
+
+pub(crate) fn write_string<W: Write>(w: &mut W, s: &str) -> io::Result<()> {
+    w.write_all(b"\"")?;
+    let mut start = 0;
+    for (i, ch) in s.char_indices() {
+        let escaped = match ch {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\u{8}' => "\\b",
+            '\u{c}' => "\\f",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            '\u{0}'..='\u{1f}' => "",
+            _ => continue,
+        };
+        w.write_all(&s.as_bytes()[start..i])?;
+        if escaped.is_empty() {
+            write!(w, "\\u{:04x}", ch as u32)?;
+        } else {
+            w.write_all(escaped.as_bytes())?;
+        }
+        start = i + ch.len_utf8();
+    }
+    w.write_all(&s.as_bytes()[start..])?;
+    w.write_all(b"\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_str, number::Number};
+
+    #[test]
+    fn test_to_string() {
+        let value = Value::Object(vec![
+            ("a".to_string(), Box::new(Value::Number(Number::from(1u64)))),
+            (
+                "b".to_string(),
+                Box::new(Value::Array(vec![
+                    Value::Boolean(true),
+                    Value::Null,
+                    Value::Number(Number::from_f64(-1.5).unwrap()),
+                    Value::String("x".to_string()),
+                ])),
+            ),
+            ("c".to_string(), Box::new(Value::Object(vec![]))),
+            ("d".to_string(), Box::new(Value::Array(vec![]))),
+        ]);
+        assert_eq!(
+            r#"{"a":1,"b":[true,null,-1.5,"x"],"c":{},"d":[]}"#,
+            to_string(&value).unwrap()
+        );
+        assert_eq!(to_string(&value).unwrap(), value.to_string());
+    }
+
+    #[test]
+    fn test_escape_string() {
+        let inputs = vec![
+            ("plain", r#""plain""#),
+            ("\"quoted\"", r#""\"quoted\"""#),
+            ("back\\slash/", r#""back\\slash/""#),
+            ("\u{8}\u{c}\n\r\t", r#""\b\f\n\r\t""#),
+            ("\u{0}\u{1f}\u{7f}", "\"\\u0000\\u001f\u{7f}\""),
+            ("日本語 \u{1f600}", "\"日本語 \u{1f600}\""),
+        ];
+        for (input, expected) in inputs {
+            assert_eq!(
+                expected,
+                to_string(&Value::String(input.to_string())).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let inputs = vec![
+            r#"{"key":"value","arr":[1,-2,3.5,1e+300],"obj":{"t":true,"f":false,"n":null}}"#,
+            r#"["\"\\\/\b\f\n\r\t\u0001😀"]"#,
+            r#"[[],{},[{}],""]"#,
+        ];
+        for input in inputs {
+            let value = from_str(input).unwrap();
+            let output = to_string(&value).unwrap();
+            assert_eq!(value, from_str(&output).unwrap(), "{}", output);
+        }
+    }
+
+    #[test]
+    fn test_pretty() {
+        let value = from_str(r#"{"a":1,"b":[true,{"c":null}],"d":{},"e":[]}"#).unwrap();
+        assert_eq!(
+            r#"{
+  "a": 1,
+  "b": [
+    true,
+    {
+      "c": null
+    }
+  ],
+  "d": {},
+  "e": []
+}"#,
+            to_string_pretty(&value).unwrap()
+        );
+
+        let formatter = PrettyFormatter::new()
+            .indent(Indent::Tabs(1))
+            .space_before_colon(true)
+            .newline(Newline::CrLf)
+            .trailing_newline(true);
+        assert_eq!(
+            "{\r\n\t\"a\" : 1,\r\n\t\"b\" : [\r\n\t\ttrue,\r\n\t\t{\r\n\t\t\t\"c\" : null\r\n\t\t}\r\n\t],\r\n\t\"d\" : {},\r\n\t\"e\" : []\r\n}\r\n",
+            formatter.to_string(&value).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_pretty_inline() {
+        let value =
+            from_str(r#"{"short":[1,2,3],"obj":{"x":1,"y":2},"long":["aaaaaaaaaa","bbbbbbbbbb"]}"#)
+                .unwrap();
+        let formatter = PrettyFormatter::new()
+            .indent(Indent::Spaces(4))
+            .max_inline_width(20);
+        assert_eq!(
+            r#"{
+    "short": [1, 2, 3],
+    "obj": {"x": 1, "y": 2},
+    "long": [
+        "aaaaaaaaaa",
+        "bbbbbbbbbb"
+    ]
+}"#,
+            formatter.to_string(&value).unwrap()
+        );
+
+        let formatter = formatter
+            .space_after_comma(false)
+            .space_after_colon(false)
+            .max_inline_width(100);
+        assert_eq!(
+            r#"{"short":[1,2,3],"obj":{"x":1,"y":2},"long":["aaaaaaaaaa","bbbbbbbbbb"]}"#,
+            formatter.to_string(&value).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_error_value() {
+        let value = Value::Array(vec![Value::Null, Value::Error("bad".to_string())]);
+        let e = to_string(&value).unwrap_err();
+        assert_eq!(crate::error::ErrorCode::Unserializable, e.code());
+        assert!(to_string_pretty(&value).is_err());
+        assert!(to_writer(Vec::new(), &value).is_err());
+        assert_eq!("[null,bad]", value.to_string());
+        assert!(PrettyFormatter::new()
+            .max_inline_width(80)
+            .to_string(&value)
+            .is_err());
+    }
+
+    #[test]
+    fn test_to_writer() {
+        let mut buf = Vec::new();
+        to_writer(&mut buf, &from_str(r#"{ "a" : [ 1 , 2 ] }"#).unwrap()).unwrap();
+        assert_eq!(br#"{"a":[1,2]}"#, &buf[..]);
+    }
+}
@@ -0,0 +1,319 @@
+use std::{
+    io::{self, Read},
+    str::Chars,
+};
+
+/// Characters the `Lexer` reads from.
+///
+/// Sources that decode bytes stop at the first invalid UTF-8 sequence and
+/// report where it was, so the lexer can tell it apart from the real end
+/// of the input. Sources backed by a reader likewise stop at a failed read.
+pub trait Source: Iterator<Item = char> {
+    fn utf8_error(&self) -> Option<usize> {
+        None
+    }
+    fn take_io_error(&mut self) -> Option<io::Error> {
+        None
+    }
+    /// Whether the chars ran out only because the rest of the input has not
+    /// arrived yet, so the lexer should stop and pick up later.
+    fn suspended(&self) -> bool {
+        false
+    }
+}
+
+impl Source for Chars<'_> {}
+
+/// Decodes UTF-8 from a byte slice while it is being lexed, without a
+/// separate validation pass over the whole input.
+///
+/// Every char is decoded, not only those in strings. ASCII takes a single
+/// comparison, and outside strings strict JSON has nothing else, so this
+/// costs no more than checking strings alone. It lets JSON5 whitespace and
+/// identifiers be non-ASCII, and a stray byte outside a string is reported
+/// as invalid UTF-8 at its exact offset rather than as some other error.
+#[derive(Debug, Clone)]
+pub struct ByteSource<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    error: Option<usize>,
+}
+
+impl<'a> ByteSource<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        ByteSource {
+            bytes,
+            pos: 0,
+            error: None,
+        }
+    }
+}
+
+impl Iterator for ByteSource<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if self.error.is_some() {
+            return None;
+        }
+        let rest = &self.bytes[self.pos..];
+        if rest.is_empty() {
+            return None;
+        }
+        match decode(rest) {
+            Some(ch) => {
+                self.pos += ch.len_utf8();
+                Some(ch)
+            }
+            None => {
+                self.error = Some(self.pos);
+                None
+            }
+        }
+    }
+}
+
+// The char at the start of `bytes`, or `None` if they do not start with a
+// complete UTF-8 sequence.
+fn decode(bytes: &[u8]) -> Option<char> {
+    let first = *bytes.first()?;
+    if first.is_ascii() {
+        return Some(first as char);
+    }
+    // A char is at most 4 bytes, so only that much needs checking.
+    let head = &bytes[..bytes.len().min(4)];
+    let valid = match std::str::from_utf8(head) {
+        Ok(s) => s,
+        Err(e) => std::str::from_utf8(&head[..e.valid_up_to()]).unwrap(),
+    };
+    valid.chars().next()
+}
+
+impl Source for ByteSource<'_> {
+    fn utf8_error(&self) -> Option<usize> {
+        self.error
+    }
+}
+
+// Decodes the part of an input received so far, `offset` bytes into it.
+// Unless it is the last part, running out of bytes is recorded: whatever
+// was being lexed may go on in the next one.
+#[derive(Debug, Clone)]
+pub(crate) struct ChunkSource<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    offset: usize,
+    last: bool,
+    exhausted: bool,
+    error: Option<usize>,
+}
+
+impl<'a> ChunkSource<'a> {
+    pub(crate) fn new(bytes: &'a [u8], offset: usize, last: bool) -> Self {
+        ChunkSource {
+            bytes,
+            pos: 0,
+            offset,
+            last,
+            exhausted: false,
+            error: None,
+        }
+    }
+}
+
+impl Iterator for ChunkSource<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if self.error.is_some() {
+            return None;
+        }
+        let rest = &self.bytes[self.pos..];
+        match decode(rest) {
+            Some(ch) => {
+                self.pos += ch.len_utf8();
+                Some(ch)
+            }
+            // A multi-byte char may be split across chunks.
+            None if !self.last && rest.len() < 4 => {
+                self.exhausted = true;
+                None
+            }
+            None if rest.is_empty() => None,
+            None => {
+                self.error = Some(self.offset + self.pos);
+                None
+            }
+        }
+    }
+}
+
+impl Source for ChunkSource<'_> {
+    fn utf8_error(&self) -> Option<usize> {
+        self.error
+    }
+    fn suspended(&self) -> bool {
+        self.exhausted
+    }
+}
+
+const READ_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Decodes UTF-8 from an `io::Read` through a fixed-size buffer, so the
+/// input never has to be in memory as a whole.
+#[derive(Debug)]
+pub struct ReadSource<R> {
+    reader: R,
+    buf: Vec<u8>,
+    start: usize,
+    end: usize,
+    // offset in the whole input of `buf[start]`
+    offset: usize,
+    eof: bool,
+    utf8_error: Option<usize>,
+    io_error: Option<io::Error>,
+    io_failed: bool,
+}
+
+impl<R: Read> ReadSource<R> {
+    pub fn new(reader: R) -> Self {
+        ReadSource {
+            reader,
+            buf: vec![0; READ_BUFFER_SIZE],
+            start: 0,
+            end: 0,
+            offset: 0,
+            eof: false,
+            utf8_error: None,
+            io_error: None,
+            io_failed: false,
+        }
+    }
+
+    // Reads until at least one complete char is buffered or the input ends.
+    fn fill(&mut self) {
+        self.buf.copy_within(self.start..self.end, 0);
+        self.end -= self.start;
+        self.start = 0;
+        while self.end < 4 && !self.eof {
+            match self.reader.read(&mut self.buf[self.end..]) {
+                Ok(0) => self.eof = true,
+                Ok(n) => self.end += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.io_error = Some(e);
+                    self.io_failed = true;
+                    self.eof = true;
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for ReadSource<R> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if self.utf8_error.is_some() {
+            return None;
+        }
+        let buffered = &self.buf[self.start..self.end];
+        // A multi-byte char may be split across reads.
+        if buffered.is_empty() || (!buffered[0].is_ascii() && buffered.len() < 4) {
+            self.fill();
+        }
+        match decode(&self.buf[self.start..self.end]) {
+            Some(ch) => {
+                self.start += ch.len_utf8();
+                self.offset += ch.len_utf8();
+                Some(ch)
+            }
+            // Bytes cut short by a failed read are not a UTF-8 error.
+            None if self.start == self.end || self.io_failed => None,
+            None => {
+                self.utf8_error = Some(self.offset);
+                None
+            }
+        }
+    }
+}
+
+impl<R: Read> Source for ReadSource<R> {
+    fn utf8_error(&self) -> Option<usize> {
+        self.utf8_error
+    }
+    fn take_io_error(&mut self) -> Option<io::Error> {
+        self.io_error.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_source() {
+        let input = "a\u{e9}\u{3042}\u{1f600}";
+        let chars: String = ByteSource::new(input.as_bytes()).collect();
+        assert_eq!(input, chars);
+
+        let inputs: Vec<(&[u8], &str, usize)> = vec![
+            (b"ab\xffcd", "ab", 2),
+            (b"a\xc3", "a", 1),
+            (b"\xe3\x81", "", 0),
+            (b"ok\xed\xa0\x80", "ok", 2),
+            (b"\xc3\xa9\xc3\x28", "\u{e9}", 2),
+        ];
+        for (input, valid, offset) in inputs {
+            let mut source = ByteSource::new(input);
+            let chars: String = source.by_ref().collect();
+            assert_eq!(valid, chars);
+            assert_eq!(Some(offset), source.utf8_error());
+        }
+    }
+
+    // Hands out at most one byte per read, then fails after `limit` bytes.
+    struct Trickle<'a> {
+        bytes: &'a [u8],
+        limit: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.limit == 0 {
+                return Err(io::Error::other("connection reset"));
+            }
+            self.limit -= 1;
+            let n = self.bytes.len().min(1);
+            buf[..n].copy_from_slice(&self.bytes[..n]);
+            self.bytes = &self.bytes[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_read_source() {
+        let input = "a\u{e9}\u{3042}\u{1f600}z".repeat(3000);
+        let chars: String = ReadSource::new(input.as_bytes()).collect();
+        assert_eq!(input, chars);
+
+        let reader = Trickle {
+            bytes: input.as_bytes(),
+            limit: usize::MAX,
+        };
+        let chars: String = ReadSource::new(reader).collect();
+        assert_eq!(input, chars);
+
+        let mut source = ReadSource::new(&b"ab\xe3\x81"[..]);
+        assert_eq!("ab", source.by_ref().collect::<String>());
+        assert_eq!(Some(2), source.utf8_error());
+
+        let reader = Trickle {
+            bytes: b"abcdef",
+            limit: 3,
+        };
+        let mut source = ReadSource::new(reader);
+        assert_eq!("abc", source.by_ref().collect::<String>());
+        assert!(source.take_io_error().is_some());
+    }
+}
@@ -0,0 +1,156 @@
+//! A `Value` that remembers where in the input each part of it came from,
+//! so that errors found after parsing can still point at the source.
+
+use std::borrow::Cow;
+
+use crate::{
+    error::JSONError,
+    first_error,
+    number::Number,
+    options::ParserOptions,
+    parser::{Parser, Tree},
+    token::{Location, Span},
+    value,
+};
+
+/// A value or object key together with its span in the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    String(String),
+    Number(Number),
+    Boolean(bool),
+    Object(Vec<(Spanned<String>, Spanned<Value>)>),
+    Array(Vec<Spanned<Value>>),
+    Null,
+}
+
+impl Spanned<Value> {
+    /// The member `key` of an object, if there is one.
+    pub fn get(&self, key: &str) -> Option<&Spanned<Value>> {
+        match &self.value {
+            Value::Object(members) => members.iter().find(|(k, _)| k.value == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Drops the spans.
+    pub fn into_value(self) -> value::Value {
+        match self.value {
+            Value::String(s) => value::Value::String(s),
+            Value::Number(n) => value::Value::Number(n),
+            Value::Boolean(b) => value::Value::Boolean(b),
+            Value::Object(members) => value::Value::Object(
+                members
+                    .into_iter()
+                    .map(|(key, value)| (key.value, Box::new(value.into_value())))
+                    .collect(),
+            ),
+            Value::Array(elements) => {
+                value::Value::Array(elements.into_iter().map(Spanned::into_value).collect())
+            }
+            Value::Null => value::Value::Null,
+        }
+    }
+}
+
+// The parser sets the real span through `with_span`.
+fn unspanned(value: Value) -> Spanned<Value> {
+    let start = Location::new(1, 1);
+    Spanned {
+        value,
+        span: Span { start, end: start },
+    }
+}
+
+impl<'a> Tree<'a> for Spanned<Value> {
+    type Members = Vec<(Spanned<String>, Spanned<Value>)>;
+
+    fn string(value: Cow<'a, str>) -> Self {
+        unspanned(Value::String(value.into_owned()))
+    }
+    fn number(value: Number) -> Self {
+        unspanned(Value::Number(value))
+    }
+    fn boolean(value: bool) -> Self {
+        unspanned(Value::Boolean(value))
+    }
+    fn null() -> Self {
+        unspanned(Value::Null)
+    }
+    fn array(elements: Vec<Self>) -> Self {
+        unspanned(Value::Array(elements))
+    }
+    fn object(members: Self::Members) -> Self {
+        unspanned(Value::Object(members))
+    }
+    fn push_member(object: &mut Self::Members, key: Cow<'a, str>, key_span: Span, value: Self) {
+        let key = Spanned {
+            value: key.into_owned(),
+            span: key_span,
+        };
+        object.push((key, value));
+    }
+    fn with_span(self, span: Span) -> Self {
+        Spanned { span, ..self }
+    }
+}
+
+/// Like `toyjson::from_str`, keeping the span of every value and key.
+pub fn from_str(source: &str) -> Result<Spanned<Value>, JSONError> {
+    from_str_with_options(source, ParserOptions::default())
+}
+
+/// Like `from_str`, in the dialect selected by `options`.
+pub fn from_str_with_options(
+    source: &str,
+    options: ParserOptions,
+) -> Result<Spanned<Value>, JSONError> {
+    first_error(Parser::with_options(source, options).parse_all_spanned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spanned() {
+        let src = "{\n  \"name\": \"toy\",\n  \"port\": \"80\",\n  \"tags\": [1, [], {}]\n}";
+        let value = from_str(src).unwrap();
+        assert_eq!(src, &src[value.span.range()]);
+
+        let port = value.get("port").unwrap();
+        assert_eq!(Value::String("80".to_string()), port.value);
+        assert_eq!("\"80\"", &src[port.span.range()]);
+        assert_eq!((3, 11), (port.span.start.line, port.span.start.column));
+
+        let Value::Object(members) = &value.value else {
+            panic!("{:?}", value);
+        };
+        let keys: Vec<_> = members.iter().map(|(k, _)| &src[k.span.range()]).collect();
+        assert_eq!(vec!["\"name\"", "\"port\"", "\"tags\""], keys);
+
+        let Value::Array(tags) = &value.get("tags").unwrap().value else {
+            panic!("{:?}", value);
+        };
+        let tags: Vec<_> = tags.iter().map(|t| &src[t.span.range()]).collect();
+        assert_eq!(vec!["1", "[]", "{}"], tags);
+
+        assert_eq!(crate::from_str(src).unwrap(), value.into_value());
+    }
+
+    #[test]
+    fn test_spanned_options() {
+        let src = "// config\n{port: 0x50, /* ok */ }";
+        let value = from_str_with_options(src, ParserOptions::json5()).unwrap();
+        assert_eq!("{port: 0x50, /* ok */ }", &src[value.span.range()]);
+        let port = value.get("port").unwrap();
+        assert_eq!("0x50", &src[port.span.range()]);
+        assert_eq!((2, 8), (port.span.start.line, port.span.start.column));
+    }
+}
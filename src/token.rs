@@ -1,18 +1,42 @@
-use std::fmt::{write, Display, Error, Formatter};
+use std::{
+    fmt::{Display, Error, Formatter},
+    ops::Range,
+};
+
+use crate::error::ErrorCode;
 
 #[derive(Debug, Clone)]
 pub struct Token {
     pub location: Location,
+    // just past the last character
+    pub end: Location,
     pub token_type: TokenType,
     pub value: Option<String>,
+    // set on ILLEGAL tokens
+    pub error_code: Option<ErrorCode>,
+    // For strings lexed without copying: the offset of the first escape, or
+    // of the closing quote. Only the contents from there on are in `value`.
+    pub prefix_end: Option<usize>,
 }
 
 impl Default for Token {
     fn default() -> Self {
         Token {
             location: Location::new(1, 1),
+            end: Location::new(1, 1),
             token_type: TokenType::ILLEGAL,
             value: None,
+            error_code: None,
+            prefix_end: None,
+        }
+    }
+}
+
+impl Token {
+    pub fn span(&self) -> Span {
+        Span {
+            start: self.location,
+            end: self.end,
         }
     }
 }
@@ -20,9 +44,10 @@ impl Default for Token {
 impl Display for Token {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self.token_type {
-            TokenType::String => write!(f, "\"{}\"", self.value.as_ref().unwrap()),
+            TokenType::String => write!(f, "\"{}\"", self.value.as_deref().unwrap_or_default()),
             TokenType::Number => write!(f, "{}", self.value.as_ref().unwrap()),
             TokenType::Ident => write!(f, " {} ", self.value.as_ref().unwrap()),
+            TokenType::Comment => write!(f, "{}", self.value.as_ref().unwrap()),
             TokenType::True => write!(f, "true"),
             TokenType::False => write!(f, "false"),
             TokenType::Null => write!(f, "null"),
@@ -32,7 +57,8 @@ impl Display for Token {
             TokenType::RightBracket => write!(f, "]"),
             TokenType::Colon => write!(f, ":"),
             TokenType::Comma => write!(f, ","),
-            TokenType::EOF => write!(f, "\n"),
+            TokenType::EOF => writeln!(f),
+            TokenType::RecordSeparator => write!(f, "\u{1e}"),
             TokenType::ILLEGAL => write!(f, "ILLEGAL"),
         }
     }
@@ -44,26 +70,28 @@ impl TokenType {
             "true" => TokenType::True,
             "false" => TokenType::False,
             "null" => TokenType::Null,
-            _ => TokenType::String,
+            _ => TokenType::Ident,
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TokenType {
-    String,       // "string"
-    Number,       // 12345 | 123.45 | 123.45e6 | 123.45e+6 | 123.45e-6
-    True,         // true
-    False,        // false
-    Null,         // null
-    Ident,        // ident
-    LeftBrace,    // {
-    RightBrace,   // }
-    LeftBracket,  // [
-    RightBracket, // ]
-    Colon,        // :
-    Comma,        // ,
-    EOF,          // EOF
+    String,          // "string"
+    Number,          // 12345 | 123.45 | 123.45e6 | 123.45e+6 | 123.45e-6
+    True,            // true
+    False,           // false
+    Null,            // null
+    Ident,           // ident
+    LeftBrace,       // {
+    RightBrace,      // }
+    LeftBracket,     // [
+    RightBracket,    // ]
+    Colon,           // :
+    Comma,           // ,
+    Comment,         // // comment | /* comment */
+    EOF,             // EOF
+    RecordSeparator, // U+001E in JSON text sequences
     ILLEGAL,
 }
 
@@ -82,7 +110,9 @@ impl Display for TokenType {
             TokenType::RightBracket => write!(f, "]"),
             TokenType::Colon => write!(f, ":"),
             TokenType::Comma => write!(f, ","),
+            TokenType::Comment => write!(f, "comment"),
             TokenType::EOF => write!(f, "EOF"),
+            TokenType::RecordSeparator => write!(f, "RS"),
             TokenType::ILLEGAL => write!(f, "ILLEGAL"),
         }
     }
@@ -108,24 +138,62 @@ pub fn lookup_ident(ident: &str) -> bool {
     IDENTIFIERS.contains(&ident)
 }
 
-#[derive(Clone, Copy, Debug)]
+/// A position in the input. `line` and `column` are 1-based, with columns
+/// counted in characters; `offset` is the 0-based byte offset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Location {
     pub line: usize,
     pub column: usize,
+    pub offset: usize,
 }
 
 impl Location {
     pub fn new(line: usize, column: usize) -> Location {
         Location {
-            line: line,
-            column: column,
+            line,
+            column,
+            offset: 0,
         }
     }
     pub fn next_column(&mut self) {
-        self.column = self.column + 1;
+        self.column += 1;
     }
     pub fn next_line(&mut self) {
-        self.line = self.line + 1;
+        self.line += 1;
         self.column = 1;
     }
+
+    // Where this location is in a larger input, given where the text it
+    // was found in starts.
+    pub(crate) fn relative_to(self, start: Location) -> Location {
+        Location {
+            line: self.line + start.line - 1,
+            column: match self.line {
+                1 => self.column + start.column - 1,
+                _ => self.column,
+            },
+            offset: self.offset + start.offset,
+        }
+    }
+}
+
+/// The range of input from `start` up to, not including, `end`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: Location,
+    pub end: Location,
+}
+
+impl Span {
+    /// The byte range, for slicing the input.
+    pub fn range(&self) -> Range<usize> {
+        self.start.offset..self.end.offset
+    }
+
+    pub(crate) fn relative_to(self, start: Location) -> Span {
+        Span {
+            start: self.start.relative_to(start),
+            end: self.end.relative_to(start),
+        }
+    }
 }
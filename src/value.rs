@@ -1,12 +1,14 @@
 use std::{
-    collections::HashMap,
+    borrow::Cow,
     fmt::{Display, Error, Formatter},
 };
 
+use crate::{number::Number, parser::Tree, ser, token::Span};
+
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub enum Value {
     String(String),
-    Number(usize),
+    Number(Number),
     Boolean(bool),
     Object(Vec<(String, Box<Value>)>),
     Array(Vec<Value>),
@@ -14,22 +16,36 @@ pub enum Value {
     Error(String),
 }
 
+/// Compact JSON. A `Value::Error` has no JSON form and is shown as its
+/// message; `ser::to_string` fails on it instead.
 impl Display for Value {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        match self {
-            Value::String(s) => write!(f, "{}", s),
-            Value::Number(n) => write!(f, "{}", n),
-            Value::Boolean(b) => write!(f, "{}", b),
-            Value::Object(o) => write!(f, "{:?}", o),
-            Value::Array(v) => {
-                write!(f, "[")?;
-                for i in v {
-                    write!(f, "{}", i)?;
-                }
-                write!(f, "]")
-            }
-            Value::Null => write!(f, "null"),
-            Value::Error(e) => write!(f, "{}", e),
-        }
+        f.write_str(&ser::to_display_string(self))
+    }
+}
+
+impl<'a> Tree<'a> for Value {
+    type Members = Vec<(String, Box<Value>)>;
+
+    fn string(value: Cow<'a, str>) -> Self {
+        Value::String(value.into_owned())
+    }
+    fn number(value: Number) -> Self {
+        Value::Number(value)
+    }
+    fn boolean(value: bool) -> Self {
+        Value::Boolean(value)
+    }
+    fn null() -> Self {
+        Value::Null
+    }
+    fn array(elements: Vec<Self>) -> Self {
+        Value::Array(elements)
+    }
+    fn object(members: Self::Members) -> Self {
+        Value::Object(members)
+    }
+    fn push_member(object: &mut Self::Members, key: Cow<'a, str>, _: Span, value: Self) {
+        object.push((key.into_owned(), Box::new(value)));
     }
 }
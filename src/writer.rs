@@ -0,0 +1,411 @@
+//! Incremental writing of a document, for output too large to be built as a
+//! `Value` first. The counterpart of `event::EventReader`.
+
+use std::io::Write;
+
+use crate::{
+    error::JSONError,
+    event::Event,
+    number::Number,
+    ser::{write_string, write_value},
+    value::Value,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Container {
+    Array,
+    Object,
+}
+
+#[derive(Debug)]
+struct Frame {
+    container: Container,
+    // nothing written in it yet, so no comma is due
+    empty: bool,
+}
+
+/// Writes compact JSON to `W` piece by piece, refusing calls that would
+/// make the output invalid, such as a value where a key is expected.
+///
+/// ```
+/// use toyjson::writer::JsonWriter;
+///
+/// let mut w = JsonWriter::new(Vec::new());
+/// w.begin_array().unwrap();
+/// for i in 0..3u64 {
+///     w.begin_object().unwrap();
+///     w.key("id").unwrap();
+///     w.number(&i.into()).unwrap();
+///     w.end_object().unwrap();
+/// }
+/// w.end_array().unwrap();
+/// let out = w.finish().unwrap();
+/// assert_eq!(r#"[{"id":0},{"id":1},{"id":2}]"#, String::from_utf8(out).unwrap());
+/// ```
+#[derive(Debug)]
+pub struct JsonWriter<W: Write> {
+    writer: W,
+    // the arrays and objects still open
+    stack: Vec<Frame>,
+    // a key was written and its value is due
+    after_key: bool,
+    // the top-level value is complete
+    done: bool,
+    // a write failed partway, so the output cannot be completed
+    failed: bool,
+}
+
+impl<W: Write> JsonWriter<W> {
+    pub fn new(writer: W) -> Self {
+        JsonWriter {
+            writer,
+            stack: vec![],
+            after_key: false,
+            done: false,
+            failed: false,
+        }
+    }
+
+    /// How many arrays and objects are open.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub fn begin_object(&mut self) -> Result<(), JSONError> {
+        self.guard(|w| w.begin(Container::Object, b"{"))
+    }
+
+    pub fn end_object(&mut self) -> Result<(), JSONError> {
+        self.guard(|w| w.end(Container::Object, b"}"))
+    }
+
+    pub fn begin_array(&mut self) -> Result<(), JSONError> {
+        self.guard(|w| w.begin(Container::Array, b"["))
+    }
+
+    pub fn end_array(&mut self) -> Result<(), JSONError> {
+        self.guard(|w| w.end(Container::Array, b"]"))
+    }
+
+    /// The key of the next member of the innermost object.
+    pub fn key(&mut self, key: &str) -> Result<(), JSONError> {
+        self.guard(|w| w.write_key(key))
+    }
+
+    fn write_key(&mut self, key: &str) -> Result<(), JSONError> {
+        match self.stack.last_mut() {
+            Some(frame) if frame.container == Container::Object && !self.after_key => {
+                let first = std::mem::replace(&mut frame.empty, false);
+                if !first {
+                    self.writer.write_all(b",")?;
+                }
+                write_string(&mut self.writer, key)?;
+                self.writer.write_all(b":")?;
+                self.after_key = true;
+                Ok(())
+            }
+            Some(frame) if frame.container == Container::Object => {
+                Err(misuse("expected a value after the key"))
+            }
+            _ => Err(misuse("a key can only be written in an object")),
+        }
+    }
+
+    /// A whole value, which may itself be an array or object. A value with
+    /// a `Value::Error` in it is refused and nothing is written.
+    pub fn value(&mut self, value: &Value) -> Result<(), JSONError> {
+        let mut buf = Vec::new();
+        write_value(&mut buf, value)?;
+        self.write_scalar(&buf)
+    }
+
+    pub fn string(&mut self, s: &str) -> Result<(), JSONError> {
+        let mut buf = Vec::new();
+        write_string(&mut buf, s)?;
+        self.write_scalar(&buf)
+    }
+
+    /// NaN and the infinities are written as null, like `to_string` does.
+    pub fn number(&mut self, n: &Number) -> Result<(), JSONError> {
+        match n.is_finite() {
+            true => self.write_scalar(n.to_string().as_bytes()),
+            false => self.write_scalar(b"null"),
+        }
+    }
+
+    pub fn boolean(&mut self, b: bool) -> Result<(), JSONError> {
+        self.write_scalar(b.to_string().as_bytes())
+    }
+
+    pub fn null(&mut self) -> Result<(), JSONError> {
+        self.write_scalar(b"null")
+    }
+
+    /// Writes what `event` stands for, so that the events of an
+    /// `EventReader` can be copied to the output as they are read.
+    pub fn event(&mut self, event: &Event) -> Result<(), JSONError> {
+        match event {
+            Event::StartObject => self.begin_object(),
+            Event::EndObject => self.end_object(),
+            Event::StartArray => self.begin_array(),
+            Event::EndArray => self.end_array(),
+            Event::Key(key) => self.key(key),
+            Event::String(s) => self.string(s),
+            Event::Number(n) => self.number(n),
+            Event::Boolean(b) => self.boolean(*b),
+            Event::Null => self.null(),
+        }
+    }
+
+    /// Checks that a complete document was written, flushes it and gives
+    /// back the underlying writer.
+    pub fn finish(mut self) -> Result<W, JSONError> {
+        if self.failed {
+            return Err(failed());
+        }
+        if !self.done {
+            return Err(misuse("the document is incomplete"));
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    // Runs `f` unless an earlier write failed, and remembers if this one
+    // does. Refused calls write nothing, so the writer can go on after them.
+    fn guard(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<(), JSONError>,
+    ) -> Result<(), JSONError> {
+        if self.failed {
+            return Err(failed());
+        }
+        let result = f(self);
+        if let Err(JSONError::IOError(_)) = result {
+            self.failed = true;
+        }
+        result
+    }
+
+    // A value written in one piece, from JSON text already formatted.
+    fn write_scalar(&mut self, text: &[u8]) -> Result<(), JSONError> {
+        self.guard(|w| {
+            w.before_value()?;
+            w.writer.write_all(text)?;
+            w.after_value();
+            Ok(())
+        })
+    }
+
+    fn begin(&mut self, container: Container, open: &[u8]) -> Result<(), JSONError> {
+        self.before_value()?;
+        self.writer.write_all(open)?;
+        self.stack.push(Frame {
+            container,
+            empty: true,
+        });
+        Ok(())
+    }
+
+    fn end(&mut self, container: Container, close: &[u8]) -> Result<(), JSONError> {
+        match self.stack.last() {
+            Some(frame) if frame.container == container && !self.after_key => {
+                self.writer.write_all(close)?;
+                self.stack.pop();
+                self.after_value();
+                Ok(())
+            }
+            Some(frame) if frame.container == container => {
+                Err(misuse("expected a value after the key"))
+            }
+            Some(_) => Err(misuse(match container {
+                Container::Array => "the innermost open container is an object",
+                Container::Object => "the innermost open container is an array",
+            })),
+            None => Err(misuse("there is nothing to close")),
+        }
+    }
+
+    // Checks that a value may go here and writes the comma before it.
+    fn before_value(&mut self) -> Result<(), JSONError> {
+        if self.done {
+            return Err(misuse("a document has a single top-level value"));
+        }
+        match self.stack.last_mut() {
+            Some(frame) if frame.container == Container::Object => {
+                if !self.after_key {
+                    return Err(misuse("expected a key in an object"));
+                }
+                self.after_key = false;
+            }
+            Some(frame) => {
+                let first = std::mem::replace(&mut frame.empty, false);
+                if !first {
+                    self.writer.write_all(b",")?;
+                }
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn after_value(&mut self) {
+        if self.stack.is_empty() {
+            self.done = true;
+        }
+    }
+}
+
+fn misuse(message: &str) -> JSONError {
+    JSONError::Misuse(message.to_string())
+}
+
+fn failed() -> JSONError {
+    misuse("an earlier write failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::ErrorCode, event::EventReader};
+
+    #[test]
+    fn test_json_writer() {
+        let mut w = JsonWriter::new(Vec::new());
+        w.begin_object().unwrap();
+        w.key("a\"b").unwrap();
+        w.begin_array().unwrap();
+        w.number(&Number::from(1u64)).unwrap();
+        w.number(&Number::from_f64(-2.5).unwrap()).unwrap();
+        w.string("x\n").unwrap();
+        w.begin_object().unwrap();
+        w.end_object().unwrap();
+        w.begin_array().unwrap();
+        w.end_array().unwrap();
+        assert_eq!(2, w.depth());
+        w.end_array().unwrap();
+        w.key("b").unwrap();
+        w.value(&crate::from_str(r#"{"c": [null]}"#).unwrap())
+            .unwrap();
+        w.key("d").unwrap();
+        w.boolean(false).unwrap();
+        w.key("e").unwrap();
+        w.null().unwrap();
+        w.end_object().unwrap();
+        let out = String::from_utf8(w.finish().unwrap()).unwrap();
+        assert_eq!(
+            r#"{"a\"b":[1,-2.5,"x\n",{},[]],"b":{"c":[null]},"d":false,"e":null}"#,
+            out
+        );
+        assert!(crate::from_str(&out).is_ok());
+
+        let mut w = JsonWriter::new(Vec::new());
+        w.string("top").unwrap();
+        assert_eq!(b"\"top\"".to_vec(), w.finish().unwrap());
+    }
+
+    #[test]
+    fn test_json_writer_misuse() {
+        type Step = fn(&mut JsonWriter<Vec<u8>>) -> Result<(), JSONError>;
+        let inputs: Vec<(Vec<Step>, &str)> = vec![
+            (
+                vec![|w| w.begin_object(), |w| w.null()],
+                "expected a key in an object",
+            ),
+            (
+                vec![|w| w.key("a")],
+                "a key can only be written in an object",
+            ),
+            (
+                vec![|w| w.begin_array(), |w| w.key("a")],
+                "a key can only be written in an object",
+            ),
+            (
+                vec![|w| w.begin_object(), |w| w.key("a"), |w| w.key("b")],
+                "expected a value after the key",
+            ),
+            (
+                vec![|w| w.begin_object(), |w| w.key("a"), |w| w.end_object()],
+                "expected a value after the key",
+            ),
+            (
+                vec![|w| w.begin_array(), |w| w.end_object()],
+                "the innermost open container is an array",
+            ),
+            (vec![|w| w.end_array()], "there is nothing to close"),
+            (
+                vec![|w| w.null(), |w| w.null()],
+                "a document has a single top-level value",
+            ),
+        ];
+        for (steps, expected) in inputs {
+            let mut w = JsonWriter::new(Vec::new());
+            let (last, init) = steps.split_last().unwrap();
+            for step in init {
+                step(&mut w).unwrap();
+            }
+            let e = last(&mut w).unwrap_err();
+            assert_eq!(expected, e.to_string());
+            assert_eq!(ErrorCode::Misuse, e.code());
+        }
+
+        let mut w = JsonWriter::new(Vec::new());
+        w.begin_array().unwrap();
+        assert_eq!(
+            "the document is incomplete",
+            w.finish().unwrap_err().to_string()
+        );
+        let w = JsonWriter::new(Vec::new());
+        assert!(w.finish().is_err());
+
+        // a refused value leaves nothing behind
+        let mut w = JsonWriter::new(Vec::new());
+        w.begin_array().unwrap();
+        let bad = Value::Array(vec![Value::Null, Value::Error("x".to_string())]);
+        let e = w.value(&bad).unwrap_err();
+        assert_eq!(ErrorCode::Unserializable, e.code());
+        assert!(w.finish().is_err());
+        let mut w = JsonWriter::new(Vec::new());
+        w.begin_array().unwrap();
+        assert!(w.value(&bad).is_err());
+        w.null().unwrap();
+        w.end_array().unwrap();
+        assert_eq!(b"[null]".to_vec(), w.finish().unwrap());
+    }
+
+    #[test]
+    fn test_json_writer_write_error() {
+        // accepts `limit` bytes, then fails
+        #[derive(Debug)]
+        struct Short(Vec<u8>, usize);
+        impl Write for Short {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                if self.0.len() + buf.len() > self.1 {
+                    return Err(std::io::Error::other("full"));
+                }
+                self.0.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut w = JsonWriter::new(Short(Vec::new(), 5));
+        w.begin_array().unwrap();
+        w.string("ab").unwrap();
+        assert_eq!(ErrorCode::Io, w.string("cd").unwrap_err().code());
+        assert_eq!(ErrorCode::Misuse, w.end_array().unwrap_err().code());
+        assert_eq!(ErrorCode::Misuse, w.finish().unwrap_err().code());
+    }
+
+    #[test]
+    fn test_copy_events() {
+        let src = r#" {"a": [1, "x", {"b": null}], "c": true, "d": {}} "#;
+        let mut w = JsonWriter::new(Vec::new());
+        for event in EventReader::new(src) {
+            w.event(&event.unwrap().value).unwrap();
+        }
+        let out = String::from_utf8(w.finish().unwrap()).unwrap();
+        assert_eq!(crate::from_str(src).unwrap().to_string(), out);
+    }
+}